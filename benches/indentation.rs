@@ -0,0 +1,34 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use yaml_parser::parse_yaml;
+
+// ネストしたマッピングとシーケンスが交互に続くドキュメント（1エントリ = 8行）
+fn nested_document(lines: usize) -> String {
+    let mut yaml = String::new();
+    for i in 0..lines / 8 {
+        yaml.push_str(&format!(
+            "entry{i}:\n  name: item-{i}\n  children:\n    - id: {i}\n      tags:\n        - a\n        - b\n  done: true\n"
+        ));
+    }
+    yaml
+}
+
+// 行あたりの時間が入力の長さに依存しないこと（インデントの追跡が線形であること）を確認する
+fn bench_indentation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("indentation");
+    group.sample_size(10);
+
+    for lines in [12_500, 25_000, 50_000] {
+        let input = nested_document(lines);
+        group.throughput(Throughput::Elements(lines as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &input, |b, input| {
+            b.iter(|| parse_yaml(black_box(input)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_indentation);
+criterion_main!(benches);
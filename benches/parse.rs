@@ -0,0 +1,80 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use yaml_parser::{parse_yaml, parse_yaml_borrowed, parse_yaml_events, parse_yaml_reader, Lexer};
+
+// サービス設定のようなブロック形式のドキュメント
+fn block_document(records: usize) -> String {
+    let mut yaml = String::from("services:\n");
+    for i in 0..records {
+        yaml.push_str(&format!(
+            "  - name: service-{i}\n    image: \"registry.example.com/app:{i}\"\n    replicas: {}\n    enabled: true\n    ratio: 0.{i}\n    ports:\n      - 80\n      - 443\n    labels:\n      tier: backend # コメント\n      owner: team-{}\n",
+            i % 7,
+            i % 13,
+        ));
+    }
+    yaml
+}
+
+// フローコレクションの多いドキュメント
+fn flow_document(records: usize) -> String {
+    let mut yaml = String::new();
+    for i in 0..records {
+        yaml.push_str(&format!("row{i}: {{id: {i}, tags: [a, b, c], point: [1.5, -2.5], name: 'ñame {i}'}}\n"));
+    }
+    yaml
+}
+
+// ブロックスカラーの多いドキュメント
+fn scalar_document(records: usize) -> String {
+    let mut yaml = String::new();
+    for i in 0..records {
+        yaml.push_str(&format!("text{i}: |\n  line one of {i}\n  line two\n\n  line four\nfolded{i}: >-\n  a folded\n  paragraph\n"));
+    }
+    yaml
+}
+
+// 整数のキーだけのマッピング（HTTP ステータスの表など）
+fn integer_key_document(records: usize) -> String {
+    let mut yaml = String::new();
+    for i in 0..records {
+        yaml.push_str(&format!("{i}: status {i}\n"));
+    }
+    yaml
+}
+
+fn bench_inputs(c: &mut Criterion) {
+    let inputs = [
+        ("block", block_document(5_000)),
+        ("flow", flow_document(10_000)),
+        ("block_scalars", scalar_document(10_000)),
+        ("integer_keys", integer_key_document(40_000)),
+    ];
+
+    for (name, input) in &inputs {
+        let mut group = c.benchmark_group(*name);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.sample_size(20);
+
+        group.bench_function("tokenize", |b| {
+            b.iter(|| Lexer::new(black_box(input)).tokenize_with_spans().unwrap())
+        });
+        group.bench_function("events", |b| {
+            b.iter(|| parse_yaml_events(black_box(input)).count())
+        });
+        group.bench_function("parse_yaml", |b| {
+            b.iter(|| parse_yaml(black_box(input)).unwrap())
+        });
+        group.bench_function("parse_yaml_borrowed", |b| {
+            b.iter(|| parse_yaml_borrowed(black_box(input)).unwrap())
+        });
+        group.bench_function("parse_yaml_reader", |b| {
+            b.iter(|| parse_yaml_reader(black_box(input.as_bytes())).unwrap())
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_inputs);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+use yaml_parser::{parse_yaml_to, YamlDeserialize};
+
+// Run with: cargo run --example derive_example --features derive
+
+#[derive(Debug, PartialEq, YamlDeserialize)]
+struct Database {
+    url: String,
+    timeout: Option<f64>,
+    #[yaml(rename = "max-connections", default)]
+    max_connections: i64,
+}
+
+#[derive(Debug, PartialEq, YamlDeserialize)]
+struct Server {
+    host: String,
+    port: i64,
+    #[yaml(default)]
+    debug: bool,
+    #[yaml(skip)]
+    request_count: i64,
+}
+
+#[derive(Debug, PartialEq, YamlDeserialize)]
+struct AppConfig {
+    name: String,
+    server: Server,
+    databases: Vec<Database>,
+    features: Option<Vec<String>>,
+}
+
+fn main() {
+    let config_yaml = r#"
+name: my-app
+server:
+  host: 0.0.0.0
+  port: 8080
+databases:
+  - url: postgresql://localhost/main
+    timeout: 30.0
+    max-connections: 20
+  - url: postgresql://localhost/replica
+features: [auth, caching]
+"#;
+
+    match parse_yaml_to::<AppConfig>(config_yaml) {
+        Ok(config) => {
+            println!("Parsed config: {:#?}", config);
+
+            assert_eq!(config.server.port, 8080);
+            assert!(!config.server.debug);
+            assert_eq!(config.databases[0].max_connections, 20);
+            assert_eq!(config.databases[1].max_connections, 0);
+            assert_eq!(config.databases[1].timeout, None);
+        }
+        Err(e) => println!("Error parsing config: {}", e),
+    }
+}
@@ -144,7 +144,7 @@ timeout: 30.5
     match parse_yaml_to::<Config>(config_yaml) {
         Ok(config) => {
             println!("Parsed config: {:?}", config);
-            assert_eq!(config.debug, true);
+            assert!(config.debug);
             assert_eq!(config.port, 8080);
             assert_eq!(config.allowed_hosts.len(), 3);
             assert_eq!(config.timeout, Some(30.5));
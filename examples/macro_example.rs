@@ -101,7 +101,7 @@ debug: true
             println!("Simple config: {:?}", config);
             assert_eq!(config.host, "localhost");
             assert_eq!(config.port, 3000);
-            assert_eq!(config.debug, true);
+            assert!(config.debug);
         }
         Err(e) => println!("Error: {}", e),
     }
@@ -152,7 +152,7 @@ features:
             
             assert_eq!(config.server.host, "0.0.0.0");
             assert_eq!(config.server.port, 8080);
-            assert_eq!(config.server.debug, false);
+            assert!(!config.server.debug);
             
             assert_eq!(config.features.len(), 4);
             assert!(config.features.contains(&"authentication".to_string()));
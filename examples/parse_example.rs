@@ -89,20 +89,20 @@ users:
             println!("Complex YAML parsed successfully:");
             print_yaml_value(&value, 0);
             
-            // 特定の値へのアクセス例
-            if let YamlValue::Object(map) = &value {
-                if let Some(YamlValue::Object(server)) = map.get("server") {
-                    if let Some(YamlValue::Integer(port)) = server.get("port") {
-                        println!("\nServer port: {}", port);
-                    }
-                }
-                
-                if let Some(YamlValue::Array(features)) = map.get("features") {
-                    println!("\nFeatures:");
-                    for feature in features {
-                        if let YamlValue::String(f) = feature {
-                            println!("  - {}", f);
-                        }
+            // 特定の値へのアクセス例（存在しないエントリは Null）
+            if let YamlValue::Integer(port) = value["server"]["port"] {
+                println!("\nServer port: {}", port);
+            }
+
+            if let Some(YamlValue::String(host)) = value.pointer("/database/connection/host") {
+                println!("\nDatabase host: {}", host);
+            }
+
+            if let YamlValue::Array(features) = &value["features"] {
+                println!("\nFeatures:");
+                for feature in features {
+                    if let YamlValue::String(f) = feature {
+                        println!("  - {}", f);
                     }
                 }
             }
@@ -129,9 +129,12 @@ fn print_yaml_value(value: &YamlValue, indent: usize) {
             println!("{}]", indent_str);
         }
         YamlValue::Object(map) => {
-            println!("{}{}", indent_str, "{");
+            println!("{}{{", indent_str);
             for (key, val) in map {
-                print!("{}{}: ", "  ".repeat(indent + 1), key);
+                match key.as_str() {
+                    Some(key) => print!("{}{}: ", "  ".repeat(indent + 1), key),
+                    None => print!("{}{:?}: ", "  ".repeat(indent + 1), key),
+                }
                 if matches!(val, YamlValue::Object(_) | YamlValue::Array(_)) {
                     println!();
                     print_yaml_value(val, indent + 1);
@@ -139,7 +142,11 @@ fn print_yaml_value(value: &YamlValue, indent: usize) {
                     print_yaml_value(val, 0);
                 }
             }
-            println!("{}{}", indent_str, "}");
+            println!("{}}}", indent_str);
+        }
+        YamlValue::Tagged(tag, inner) => {
+            print!("{}{} ", indent_str, tag);
+            print_yaml_value(inner, 0);
         }
     }
 }
\ No newline at end of file
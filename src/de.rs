@@ -0,0 +1,577 @@
+//! Serde support for reading YAML (enabled with the `serde` feature)
+//!
+//! `&YamlValue` implements `serde::Deserializer`, so any type deriving
+//! `serde::Deserialize` can be read from a parsed document.
+//!
+//! Enums use the externally tagged form: a unit variant is a plain string,
+//! other variants are a single-entry mapping from the variant name to its
+//! content. A YAML tag also selects a variant (`!Circle 1.5`); elsewhere tags
+//! are ignored.
+//!
+//! ```rust
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//!     mode: Mode,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! enum Mode {
+//!     Plain,
+//!     Tls { cert: String },
+//! }
+//!
+//! let server: Server = yaml_parser::from_str("host: localhost\nport: 443\nmode:\n  Tls:\n    cert: a.pem").unwrap();
+//! assert_eq!(server.port, 443);
+//! ```
+
+use std::fmt;
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::error::{Result, YamlError};
+use crate::lexer::Lexer;
+use crate::mapping::{self, key_text, Mapping};
+use crate::parser::Parser;
+use crate::value::YamlValue;
+
+impl de::Error for YamlError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        YamlError::invalid_value(msg.to_string())
+    }
+}
+
+/// Deserialize an instance of `T` from YAML text
+///
+/// # Arguments
+///
+/// * `input` - A string slice containing a single YAML document
+///
+/// # Returns
+///
+/// Returns a `Result<T>`; deserialization errors carry the path and source
+/// position of the offending node
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// let ports: HashMap<String, u16> = yaml_parser::from_str("http: 80\nhttps: 443").unwrap();
+/// assert_eq!(ports["https"], 443);
+/// ```
+pub fn from_str<T: DeserializeOwned>(input: &str) -> Result<T> {
+    let mut parser = Parser::from_lexer(Lexer::new(input)).track_node_spans();
+    let value = parser.parse()?;
+    T::deserialize(&value).map_err(|error| parser.locate(error))
+}
+
+/// Deserialize an instance of `T` from a parsed YamlValue
+///
+/// # Arguments
+///
+/// * `value` - The YamlValue to read from
+///
+/// # Returns
+///
+/// Returns a `Result<T>`; errors carry the path of the offending node
+pub fn from_value<'de, T: de::Deserialize<'de>>(value: &'de YamlValue) -> Result<T> {
+    T::deserialize(value)
+}
+
+impl YamlValue {
+    // serde の invalid_type 用の表現
+    fn unexpected(&self) -> de::Unexpected<'_> {
+        match self {
+            YamlValue::String(s) => de::Unexpected::Str(s),
+            YamlValue::Integer(i) => de::Unexpected::Signed(*i),
+            YamlValue::Float(f) => de::Unexpected::Float(*f),
+            YamlValue::Boolean(b) => de::Unexpected::Bool(*b),
+            YamlValue::Array(_) => de::Unexpected::Seq,
+            YamlValue::Object(_) => de::Unexpected::Map,
+            YamlValue::Null => de::Unexpected::Unit,
+            YamlValue::Tagged(..) => de::Unexpected::Other("tagged value"),
+        }
+    }
+
+    fn invalid_type(&self, expected: &dyn de::Expected) -> YamlError {
+        de::Error::invalid_type(self.unexpected(), expected)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &'de YamlValue {
+    type Error = YamlError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            YamlValue::String(s) => visitor.visit_borrowed_str(s),
+            YamlValue::Integer(i) => visitor.visit_i64(*i),
+            YamlValue::Float(f) => visitor.visit_f64(*f),
+            YamlValue::Boolean(b) => visitor.visit_bool(*b),
+            YamlValue::Array(items) => visit_sequence(items, visitor),
+            YamlValue::Object(map) => visit_mapping(map, visitor),
+            YamlValue::Null => visitor.visit_unit(),
+            // タグは enum 以外では無視する
+            YamlValue::Tagged(_, value) => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            YamlValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            YamlValue::Null => visitor.visit_unit(),
+            _ => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            // ユニットバリアント: `Plain`
+            YamlValue::String(variant) => visitor.visit_enum(EnumDeserializer { variant, value: None }),
+            // タグ付きの値: `!Tls {cert: a.pem}`
+            YamlValue::Tagged(tag, value) => {
+                let variant = tag.strip_prefix('!').unwrap_or(tag);
+                visitor.visit_enum(EnumDeserializer { variant, value: Some(value) })
+            }
+            // その他のバリアント: `{Tls: {cert: a.pem}}`
+            YamlValue::Object(map) if map.len() == 1 && map.keys().all(|key| key.as_str().is_some()) => {
+                let (variant, value) = map.iter().next().unwrap();
+                let variant = variant.as_str().unwrap_or_default();
+                visitor
+                    .visit_enum(EnumDeserializer { variant, value: Some(value) })
+                    .map_err(|e| e.in_field(variant))
+            }
+            _ => Err(de::Error::invalid_value(self.unexpected(), &"a variant name or a single-entry mapping")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+fn visit_sequence<'de, V: Visitor<'de>>(items: &'de [YamlValue], visitor: V) -> Result<V::Value> {
+    let mut seq = SeqDeserializer { iter: items.iter().enumerate() };
+    let value = visitor.visit_seq(&mut seq)?;
+    match seq.iter.len() {
+        0 => Ok(value),
+        remaining => Err(de::Error::invalid_length(items.len() - remaining, &"fewer elements in sequence")),
+    }
+}
+
+fn visit_mapping<'de, V: Visitor<'de>>(map: &'de Mapping, visitor: V) -> Result<V::Value> {
+    visitor.visit_map(MapDeserializer { iter: map.iter(), value: None })
+}
+
+struct SeqDeserializer<'de> {
+    iter: std::iter::Enumerate<std::slice::Iter<'de, YamlValue>>,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer<'de> {
+    type Error = YamlError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.iter.next() {
+            Some((index, item)) => seed.deserialize(item).map(Some).map_err(|e| e.at_index(index)),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer<'de> {
+    iter: mapping::Iter<'de>,
+    value: Option<(&'de YamlValue, &'de YamlValue)>, // next_value で読む値とそのキー
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer<'de> {
+    type Error = YamlError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some((key, value));
+                seed.deserialize(KeyDeserializer { key }).map(Some).map_err(|e| e.in_field(&key_text(key)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| <YamlError as de::Error>::custom("value requested before key"))?;
+        seed.deserialize(value).map_err(|e| e.in_field(&key_text(key)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+// マッピングのキー。文字列のキーから数値や真偽値の型が要求された場合は
+// ここで変換し（`HashMap<u32, _>` と `"80": http`）、数値などのキーから
+// 文字列が要求された場合はその表記を渡す（`HashMap<String, _>` と `80: http`）
+struct KeyDeserializer<'de> {
+    key: &'de YamlValue,
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                let YamlValue::String(key) = self.key else {
+                    return self.key.$method(visitor);
+                };
+                match key.parse() {
+                    Ok(parsed) => visitor.$visit(parsed),
+                    Err(_) => visitor.visit_borrowed_str(key),
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! deserialize_key_text {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                match self.key {
+                    YamlValue::String(key) => visitor.visit_borrowed_str(key),
+                    YamlValue::Integer(_) | YamlValue::Float(_) | YamlValue::Boolean(_) => {
+                        visitor.visit_string(key_text(self.key).into_owned())
+                    }
+                    key => key.$method(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = YamlError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.key.deserialize_any(visitor)
+    }
+
+    deserialize_key_text! {
+        deserialize_str deserialize_string deserialize_identifier
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.key {
+            YamlValue::String(variant) => visitor.visit_enum(EnumDeserializer { variant, value: None }),
+            key => key.deserialize_enum(_name, _variants, visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        char bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct ignored_any
+    }
+}
+
+struct EnumDeserializer<'de> {
+    variant: &'de str,
+    value: Option<&'de YamlValue>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer<'de> {
+    type Error = YamlError;
+    type Variant = VariantDeserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer<'de> {
+    value: Option<&'de YamlValue>,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer<'de> {
+    type Error = YamlError;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None | Some(YamlValue::Null) => Ok(()),
+            Some(value) => Err(value.invalid_type(&"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Some(YamlValue::Array(items)) => visit_sequence(items, visitor),
+            Some(value) => Err(value.invalid_type(&"tuple variant")),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"tuple variant")),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        match self.value {
+            Some(YamlValue::Object(map)) => visit_mapping(map, visitor),
+            Some(value) => Err(value.invalid_type(&"struct variant")),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"struct variant")),
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for YamlValue {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(YamlValueVisitor)
+    }
+}
+
+struct YamlValueVisitor;
+
+impl<'de> Visitor<'de> for YamlValueVisitor {
+    type Value = YamlValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any YAML value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> std::result::Result<YamlValue, E> {
+        Ok(YamlValue::Boolean(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> std::result::Result<YamlValue, E> {
+        Ok(YamlValue::Integer(i))
+    }
+
+    fn visit_u64<E: de::Error>(self, u: u64) -> std::result::Result<YamlValue, E> {
+        i64::try_from(u)
+            .map(YamlValue::Integer)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(u), &"an integer that fits in i64"))
+    }
+
+    fn visit_f64<E>(self, f: f64) -> std::result::Result<YamlValue, E> {
+        Ok(YamlValue::Float(f))
+    }
+
+    fn visit_str<E>(self, s: &str) -> std::result::Result<YamlValue, E> {
+        Ok(YamlValue::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> std::result::Result<YamlValue, E> {
+        Ok(YamlValue::String(s))
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<YamlValue, E> {
+        Ok(YamlValue::Null)
+    }
+
+    fn visit_none<E>(self) -> std::result::Result<YamlValue, E> {
+        Ok(YamlValue::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> std::result::Result<YamlValue, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<YamlValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(YamlValue::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> std::result::Result<YamlValue, A::Error> {
+        let mut map = Mapping::new();
+        while let Some((key, value)) = access.next_entry::<YamlValue, YamlValue>()? {
+            map.insert(key, value);
+        }
+        Ok(YamlValue::Object(map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        port: u16,
+        ratio: f32,
+        debug: bool,
+        tags: Vec<String>,
+        limits: Option<Limits>,
+        #[serde(default)]
+        retries: u8,
+        point: (i32, i32),
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Limits {
+        cpu: f64,
+        memory: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Line(i32, i32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Meters(f64);
+
+    #[test]
+    fn test_deserialize_struct() {
+        let yaml = "name: app\nport: 8080\nratio: 1\ndebug: false\ntags: [a, b]\nlimits:\n  cpu: 0.5\n  memory: 1Gi\npoint: [3, -4]";
+        let config: Config = from_str(yaml).unwrap();
+        assert_eq!(config, Config {
+            name: "app".to_string(),
+            port: 8080,
+            ratio: 1.0,
+            debug: false,
+            tags: vec!["a".to_string(), "b".to_string()],
+            limits: Some(Limits { cpu: 0.5, memory: "1Gi".to_string() }),
+            retries: 0,
+            point: (3, -4),
+        });
+    }
+
+    #[test]
+    fn test_deserialize_enums() {
+        let shapes: Vec<Shape> = from_str("- Empty\n- Circle: 1.5\n- Line: [1, 2]\n- Rect:\n    w: 3\n    h: 4").unwrap();
+        assert_eq!(shapes, vec![
+            Shape::Empty,
+            Shape::Circle(1.5),
+            Shape::Line(1, 2),
+            Shape::Rect { w: 3, h: 4 },
+        ]);
+        assert!(from_str::<Shape>("Hexagon").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_tagged_values() {
+        let shapes: Vec<Shape> = from_str("- !Empty\n- !Circle 1.5\n- !Line [1, 2]\n- !Rect {w: 3, h: 4}").unwrap();
+        assert_eq!(shapes, vec![
+            Shape::Empty,
+            Shape::Circle(1.5),
+            Shape::Line(1, 2),
+            Shape::Rect { w: 3, h: 4 },
+        ]);
+
+        // enum 以外ではタグを無視する
+        let names: Vec<String> = from_str("[!Ref a, b]").unwrap();
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_deserialize_maps_and_newtypes() {
+        let ports: BTreeMap<u16, String> = from_str("80: http\n443: https").unwrap();
+        assert_eq!(ports.get(&443).map(String::as_str), Some("https"));
+
+        let distances: HashMap<String, Meters> = from_str("run: 5000\nswim: 1.5").unwrap();
+        assert_eq!(distances["swim"], Meters(1.5));
+
+        let unit: () = from_str("~").unwrap();
+        assert_eq!(unit, ());
+    }
+
+    #[test]
+    fn test_deserialize_non_string_keys() {
+        // 数値や真偽値のキーは文字列としても読める
+        let names: HashMap<String, String> = from_str("200: OK\ntrue: yes").unwrap();
+        assert_eq!(names["200"], "OK");
+        assert_eq!(names["true"], "yes");
+
+        let codes: BTreeMap<i64, bool> = from_str("{404: false, '500': true}").unwrap();
+        assert_eq!(codes.get(&404), Some(&false));
+        assert_eq!(codes.get(&500), Some(&true));
+
+        let pairs: BTreeMap<Vec<i32>, String> = from_str("? [1, 2]\n: pair\n").unwrap();
+        assert_eq!(pairs[&vec![1, 2]], "pair");
+    }
+
+    #[test]
+    fn test_deserialize_yaml_value() {
+        let yaml = "a: [1, x]\nb: ~";
+        let value: YamlValue = from_str(yaml).unwrap();
+        assert_eq!(value, crate::parse_yaml(yaml).unwrap());
+    }
+
+    #[test]
+    fn test_error_path_and_position() {
+        let error = from_str::<Config>("name: app\nport: 8080\nratio: 1\ndebug: false\ntags: [a, 7]\npoint: [0, 0]")
+            .unwrap_err();
+        assert_eq!(error.path(), Some("tags[1]"));
+        assert_eq!(error.span().map(|span| (span.line, span.column)), Some((5, 11)));
+
+        let error = from_str::<Config>("name: app").unwrap_err();
+        assert!(error.to_string().contains("missing field `port`"));
+    }
+}
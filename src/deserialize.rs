@@ -1,4 +1,5 @@
 use crate::error::{Result, YamlError};
+use crate::mapping::Mapping;
 use crate::value::YamlValue;
 use std::collections::HashMap;
 
@@ -20,7 +21,7 @@ impl YamlDeserialize for String {
     fn from_yaml(value: &YamlValue) -> Result<Self> {
         match value {
             YamlValue::String(s) => Ok(s.clone()),
-            _ => Err(YamlError::InvalidValue(format!("Expected string, found {:?}", value))),
+            _ => Err(YamlError::invalid_value(format!("Expected string, found {:?}", value))),
         }
     }
 }
@@ -29,7 +30,7 @@ impl YamlDeserialize for i64 {
     fn from_yaml(value: &YamlValue) -> Result<Self> {
         match value {
             YamlValue::Integer(i) => Ok(*i),
-            _ => Err(YamlError::InvalidValue(format!("Expected integer, found {:?}", value))),
+            _ => Err(YamlError::invalid_value(format!("Expected integer, found {:?}", value))),
         }
     }
 }
@@ -38,7 +39,7 @@ impl YamlDeserialize for i32 {
     fn from_yaml(value: &YamlValue) -> Result<Self> {
         match value {
             YamlValue::Integer(i) => Ok(*i as i32),
-            _ => Err(YamlError::InvalidValue(format!("Expected integer, found {:?}", value))),
+            _ => Err(YamlError::invalid_value(format!("Expected integer, found {:?}", value))),
         }
     }
 }
@@ -48,7 +49,7 @@ impl YamlDeserialize for f64 {
         match value {
             YamlValue::Float(f) => Ok(*f),
             YamlValue::Integer(i) => Ok(*i as f64),
-            _ => Err(YamlError::InvalidValue(format!("Expected float, found {:?}", value))),
+            _ => Err(YamlError::invalid_value(format!("Expected float, found {:?}", value))),
         }
     }
 }
@@ -57,7 +58,7 @@ impl YamlDeserialize for bool {
     fn from_yaml(value: &YamlValue) -> Result<Self> {
         match value {
             YamlValue::Boolean(b) => Ok(*b),
-            _ => Err(YamlError::InvalidValue(format!("Expected boolean, found {:?}", value))),
+            _ => Err(YamlError::invalid_value(format!("Expected boolean, found {:?}", value))),
         }
     }
 }
@@ -67,12 +68,12 @@ impl<T: YamlDeserialize> YamlDeserialize for Vec<T> {
         match value {
             YamlValue::Array(arr) => {
                 let mut result = Vec::new();
-                for item in arr {
-                    result.push(T::from_yaml(item)?);
+                for (index, item) in arr.iter().enumerate() {
+                    result.push(T::from_yaml(item).map_err(|e| e.at_index(index))?);
                 }
                 Ok(result)
             }
-            _ => Err(YamlError::InvalidValue(format!("Expected array, found {:?}", value))),
+            _ => Err(YamlError::invalid_value(format!("Expected array, found {:?}", value))),
         }
     }
 }
@@ -86,38 +87,57 @@ impl<T: YamlDeserialize> YamlDeserialize for Option<T> {
     }
 }
 
-impl YamlDeserialize for HashMap<String, YamlValue> {
+impl YamlDeserialize for Mapping {
     fn from_yaml(value: &YamlValue) -> Result<Self> {
         match value {
             YamlValue::Object(map) => Ok(map.clone()),
-            _ => Err(YamlError::InvalidValue(format!("Expected object, found {:?}", value))),
+            _ => Err(YamlError::invalid_value(format!("Expected object, found {:?}", value))),
+        }
+    }
+}
+
+impl YamlDeserialize for HashMap<String, YamlValue> {
+    fn from_yaml(value: &YamlValue) -> Result<Self> {
+        match value {
+            YamlValue::Object(map) => Ok(map.clone().into()),
+            _ => Err(YamlError::invalid_value(format!("Expected object, found {:?}", value))),
         }
     }
 }
 
 /// Extract a field from a YAML object
+/// 
+/// Errors from the field's own deserialization are prefixed with `field_name`,
+/// so nested failures report their full path (e.g. `database.replicas[2].port`).
 pub fn extract_field<T: YamlDeserialize>(value: &YamlValue, field_name: &str) -> Result<T> {
     match value {
         YamlValue::Object(map) => {
             match map.get(field_name) {
-                Some(field_value) => T::from_yaml(field_value),
-                None => Err(YamlError::InvalidValue(format!("Missing field: {}", field_name))),
+                Some(field_value) => T::from_yaml(field_value).map_err(|e| e.in_field(field_name)),
+                None => Err(YamlError::invalid_value(format!("Missing field: {}", field_name))),
             }
         }
-        _ => Err(YamlError::InvalidValue(format!("Expected object to extract field {}", field_name))),
+        _ => Err(YamlError::invalid_value(format!("Expected object to extract field {}", field_name))),
     }
 }
 
 /// Extract an optional field from a YAML object
+/// 
+/// A missing field yields `None`; a present field, including an explicit
+/// `null`, is passed to `T::from_yaml`. To treat `null` like a missing field,
+/// extract an `Option<T>` and flatten the result, as
+/// `#[derive(YamlDeserialize)]` does for `Option` and `#[yaml(default)]` fields.
 pub fn extract_optional_field<T: YamlDeserialize>(value: &YamlValue, field_name: &str) -> Result<Option<T>> {
     match value {
         YamlValue::Object(map) => {
             match map.get(field_name) {
-                Some(field_value) => Ok(Some(T::from_yaml(field_value)?)),
+                Some(field_value) => T::from_yaml(field_value)
+                    .map(Some)
+                    .map_err(|e| e.in_field(field_name)),
                 None => Ok(None), // Missing field is Ok for Option
             }
         }
-        _ => Err(YamlError::InvalidValue(format!("Expected object to extract field {}", field_name))),
+        _ => Err(YamlError::invalid_value(format!("Expected object to extract field {}", field_name))),
     }
 }
 
@@ -175,4 +195,52 @@ mod tests {
         let result: Option<String> = YamlDeserialize::from_yaml(&value).unwrap();
         assert_eq!(result, Some("test".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_optional_field_null_or_missing() {
+        let mut map = Mapping::new();
+        map.insert("email".to_string(), YamlValue::Null);
+        let value = YamlValue::Object(map);
+
+        // 明示的な null は T::from_yaml に渡す
+        let email: Option<Option<String>> = extract_optional_field(&value, "email").unwrap();
+        assert_eq!(email, Some(None));
+        assert!(extract_optional_field::<String>(&value, "email").is_err());
+        let phone: Option<String> = extract_optional_field(&value, "phone").unwrap();
+        assert_eq!(phone, None);
+    }
+
+    #[derive(Debug)]
+    struct Replica {
+        #[allow(dead_code)]
+        port: i64,
+    }
+
+    impl YamlDeserialize for Replica {
+        fn from_yaml(value: &YamlValue) -> Result<Self> {
+            Ok(Replica { port: extract_field(value, "port")? })
+        }
+    }
+
+    #[test]
+    fn test_error_path() {
+        let replica = |port: YamlValue| {
+            let mut map = Mapping::new();
+            map.insert("port".to_string(), port);
+            YamlValue::Object(map)
+        };
+        let mut database = Mapping::new();
+        database.insert("replicas".to_string(), YamlValue::Array(vec![
+            replica(YamlValue::Integer(5432)),
+            replica(YamlValue::String("abc".to_string())),
+        ]));
+        let mut root = Mapping::new();
+        root.insert("database".to_string(), YamlValue::Object(database.clone()));
+
+        let error = extract_field::<Vec<Replica>>(&YamlValue::Object(database), "replicas").unwrap_err();
+        assert_eq!(error.path(), Some("replicas[1].port"));
+
+        let error = extract_field::<i64>(&YamlValue::Object(root), "missing").unwrap_err();
+        assert_eq!(error.path(), Some(""));
+    }
+}
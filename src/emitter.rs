@@ -0,0 +1,671 @@
+use crate::schema::{short_tag, Schema};
+use crate::value::YamlValue;
+
+/// How strings are quoted when written out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuoteStyle {
+    /// Write strings plain unless they would re-parse as another value
+    /// (a boolean, number or null) or contain YAML syntax; such strings are
+    /// double-quoted.
+    Minimal,
+    /// Single-quote every string value, falling back to double quotes when
+    /// the string needs escape sequences.
+    Single,
+    /// Double-quote every string value.
+    Double,
+}
+
+/// Options controlling the layout of emitted YAML.
+#[derive(Debug, Clone)]
+pub struct EmitterOptions {
+    /// Number of spaces per nesting level.
+    pub indent: usize,
+    /// Indent block sequences nested under a mapping key (`key:\n  - a`)
+    /// instead of aligning them with the key (`key:\n- a`).
+    pub indent_sequences: bool,
+    /// Quoting policy for string values. Keys are only quoted when needed.
+    pub quote_style: QuoteStyle,
+    /// Write collections containing only scalars in flow style (`[a, b]`)
+    /// when the flow form is at most this many characters wide.
+    /// `None` always uses block style for non-empty collections.
+    pub max_flow_width: Option<usize>,
+    /// Write multi-line strings as literal block scalars (`|`) instead of
+    /// double-quoted strings with `\n` escapes.
+    pub literal_block_strings: bool,
+}
+
+impl Default for EmitterOptions {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            indent_sequences: true,
+            quote_style: QuoteStyle::Minimal,
+            max_flow_width: None,
+            literal_block_strings: true,
+        }
+    }
+}
+
+// スカラーを書き出す位置（引用符が必要な文字が異なる）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Context {
+    Block,
+    Flow,
+    Key,
+}
+
+/// Serializes `YamlValue` trees back to YAML text.
+///
+/// The output re-parses with [`parse_yaml`](crate::parse_yaml) to a value
+/// equal to the input.
+pub struct Emitter {
+    options: EmitterOptions,
+}
+
+impl Emitter {
+    pub fn new(options: EmitterOptions) -> Self {
+        Self { options }
+    }
+
+    /// Emit a single document. The output always ends with a newline.
+    pub fn emit(&self, value: &YamlValue) -> String {
+        let mut out = String::new();
+
+        match value {
+            YamlValue::Object(map) if !map.is_empty() && !self.fits_flow(value) => {
+                self.write_mapping(&mut out, value, 0, false);
+            }
+            YamlValue::Array(items) if !items.is_empty() && !self.fits_flow(value) => {
+                self.write_sequence(&mut out, items, 0, false);
+            }
+            YamlValue::String(s) if self.use_literal_block(s) => {
+                self.write_literal_block(&mut out, s, 0);
+            }
+            YamlValue::Tagged(tag, inner) if !self.is_inline(inner) => {
+                out.push_str(&short_tag(tag));
+                out.push('\n');
+                self.write_block(&mut out, inner, 0);
+            }
+            _ => {
+                out.push_str(&self.inline_value(value, Context::Block));
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+
+    fn write_indent(&self, out: &mut String, indent: usize) {
+        out.push_str(&" ".repeat(indent));
+    }
+
+    // タグの次の行から始まるブロックコレクション
+    fn write_block(&self, out: &mut String, value: &YamlValue, indent: usize) {
+        match value {
+            YamlValue::Object(_) => self.write_mapping(out, value, indent, false),
+            YamlValue::Array(items) => self.write_sequence(out, items, indent, false),
+            _ => {
+                self.write_indent(out, indent);
+                out.push_str(&self.inline_value(value, Context::Block));
+                out.push('\n');
+            }
+        }
+    }
+
+    // ブロックマッピング。`first_inline` の場合、最初の行は "- " の後に続く
+    fn write_mapping(&self, out: &mut String, map: &YamlValue, indent: usize, first_inline: bool) {
+        let YamlValue::Object(map) = map else {
+            return;
+        };
+
+        // キーはドキュメントの順序のまま出力する
+        for (i, (key, value)) in map.iter().enumerate() {
+            if i > 0 || !first_inline {
+                self.write_indent(out, indent);
+            }
+
+            // コレクションやタグ付きのキーは `? key` の次の行に `:` を書く
+            let explicit = is_complex_key(key);
+            if explicit {
+                out.push_str("? ");
+                out.push_str(&self.inline_value(key, Context::Flow));
+                out.push('\n');
+                self.write_indent(out, indent);
+            } else {
+                out.push_str(&self.format_key(key, Context::Key));
+            }
+            out.push(':');
+
+            match value {
+                YamlValue::Object(_) if !self.is_inline(value) => {
+                    out.push('\n');
+                    self.write_mapping(out, value, indent + self.options.indent, false);
+                }
+                YamlValue::Array(items) if !self.is_inline(value) => {
+                    out.push('\n');
+                    // 行頭の `:` の後のシーケンスは常にインデントする
+                    let seq_indent = if self.options.indent_sequences || explicit {
+                        indent + self.options.indent
+                    } else {
+                        indent
+                    };
+                    self.write_sequence(out, items, seq_indent, false);
+                }
+                // タグ付きのコレクションは常に一段深くインデントする
+                YamlValue::Tagged(tag, inner) if !self.is_inline(inner) => {
+                    out.push(' ');
+                    out.push_str(&short_tag(tag));
+                    out.push('\n');
+                    self.write_block(out, inner, indent + self.options.indent);
+                }
+                _ => self.write_inline_after_indicator(out, value, indent),
+            }
+        }
+    }
+
+    // ブロックシーケンス。`first_inline` の場合、最初の行は "- " の後に続く
+    fn write_sequence(&self, out: &mut String, items: &[YamlValue], indent: usize, first_inline: bool) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 || !first_inline {
+                self.write_indent(out, indent);
+            }
+            out.push('-');
+
+            // ネストしたコレクションは "- key: value" / "- - item" の形で書く
+            match item {
+                YamlValue::Object(_) if !self.is_inline(item) => {
+                    out.push(' ');
+                    self.write_mapping(out, item, indent + 2, true);
+                }
+                YamlValue::Array(nested) if !self.is_inline(item) => {
+                    out.push(' ');
+                    self.write_sequence(out, nested, indent + 2, true);
+                }
+                YamlValue::Tagged(tag, inner) if !self.is_inline(inner) => {
+                    out.push(' ');
+                    out.push_str(&short_tag(tag));
+                    out.push('\n');
+                    self.write_block(out, inner, indent + 2);
+                }
+                _ => self.write_inline_after_indicator(out, item, indent),
+            }
+        }
+    }
+
+    // `key:` または `-` の後の同じ行に収まる値
+    fn write_inline_after_indicator(&self, out: &mut String, value: &YamlValue, indent: usize) {
+        match value {
+            YamlValue::String(s) if self.use_literal_block(s) => {
+                out.push(' ');
+                self.write_literal_block(out, s, indent);
+            }
+            YamlValue::Tagged(tag, inner) if !inner.is_null() => {
+                out.push(' ');
+                out.push_str(&short_tag(tag));
+                self.write_inline_after_indicator(out, inner, indent);
+            }
+            _ => {
+                out.push(' ');
+                out.push_str(&self.inline_value(value, Context::Block));
+                out.push('\n');
+            }
+        }
+    }
+
+    fn write_literal_block(&self, out: &mut String, s: &str, indent: usize) {
+        let trailing_newlines = s.len() - s.trim_end_matches('\n').len();
+        out.push_str(match trailing_newlines {
+            0 => "|-",
+            1 => "|",
+            _ => "|+",
+        });
+        out.push('\n');
+
+        let content = s.trim_end_matches('\n');
+        for line in content.split('\n') {
+            if !line.is_empty() {
+                self.write_indent(out, indent + self.options.indent);
+                out.push_str(line);
+            }
+            out.push('\n');
+        }
+
+        // 保持（+）の場合、末尾の空行をそのまま書く
+        for _ in 1..trailing_newlines {
+            out.push('\n');
+        }
+    }
+
+    // 同じ行に書ける値か（スカラー、空のコレクション、フロー形式に収まるコレクション）
+    fn is_inline(&self, value: &YamlValue) -> bool {
+        match value {
+            YamlValue::Array(items) => items.is_empty() || self.fits_flow(value),
+            YamlValue::Object(map) => map.is_empty() || self.fits_flow(value),
+            YamlValue::Tagged(_, inner) => self.is_inline(inner),
+            _ => true,
+        }
+    }
+
+    fn fits_flow(&self, value: &YamlValue) -> bool {
+        let Some(max_width) = self.options.max_flow_width else {
+            return false;
+        };
+
+        let is_scalar = |v: &YamlValue| match v {
+            YamlValue::Array(_) | YamlValue::Object(_) | YamlValue::Tagged(..) => false,
+            YamlValue::String(s) => !s.contains('\n'),
+            _ => true,
+        };
+
+        let eligible = match value {
+            YamlValue::Array(items) => items.iter().all(is_scalar),
+            YamlValue::Object(map) => map.iter().all(|(k, v)| is_scalar(k) && is_scalar(v)),
+            _ => false,
+        };
+
+        eligible && self.inline_value(value, Context::Flow).chars().count() <= max_width
+    }
+
+    // 1行で書ける形式（スカラー、またはフロー形式のコレクション）
+    fn inline_value(&self, value: &YamlValue, context: Context) -> String {
+        match value {
+            YamlValue::String(s) => self.format_string(s, context),
+            YamlValue::Integer(i) => i.to_string(),
+            YamlValue::Float(f) => format_float(*f),
+            YamlValue::Boolean(b) => b.to_string(),
+            YamlValue::Null => "null".to_string(),
+            // 値のないタグ付きノードはタグだけを書く
+            YamlValue::Tagged(tag, inner) if inner.is_null() => short_tag(tag).into_owned(),
+            YamlValue::Tagged(tag, inner) => format!("{} {}", short_tag(tag), self.inline_value(inner, context)),
+            YamlValue::Array(items) => {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|item| self.inline_value(item, Context::Flow))
+                    .collect();
+                format!("[{}]", items.join(", "))
+            }
+            YamlValue::Object(map) => {
+                let entries: Vec<String> = map
+                    .iter()
+                    .map(|(k, v)| {
+                        format!("{}: {}", self.format_key(k, Context::Flow), self.inline_value(v, Context::Flow))
+                    })
+                    .collect();
+                format!("{{{}}}", entries.join(", "))
+            }
+        }
+    }
+
+    // マッピングのキー。コレクションとタグ付きのキーはフロー形式の中でだけ
+    // `? key` として書ける（ブロックでは write_mapping が書く）
+    fn format_key(&self, key: &YamlValue, context: Context) -> String {
+        match key {
+            YamlValue::String(s) => self.format_string(s, context),
+            key if is_complex_key(key) => format!("? {}", self.inline_value(key, Context::Flow)),
+            key => self.inline_value(key, Context::Flow),
+        }
+    }
+
+    fn use_literal_block(&self, s: &str) -> bool {
+        self.options.literal_block_strings && is_literal_block_safe(s)
+    }
+
+    fn format_string(&self, s: &str, context: Context) -> String {
+        if needs_escapes(s) {
+            return double_quoted(s);
+        }
+
+        match (self.options.quote_style, context) {
+            (QuoteStyle::Double, Context::Block | Context::Flow) => double_quoted(s),
+            (QuoteStyle::Single, Context::Block | Context::Flow) => format!("'{}'", s.replace('\'', "''")),
+            _ if needs_quotes(s, context) => double_quoted(s),
+            _ => s.to_string(),
+        }
+    }
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self::new(EmitterOptions::default())
+    }
+}
+
+// 1行のフロー形式の表記（エラーのパスに使うキーなど）
+pub(crate) fn flow_text(value: &YamlValue) -> String {
+    Emitter::default().inline_value(value, Context::Flow)
+}
+
+// 整数として再解析されないよう、常に小数点または指数を含める
+fn format_float(f: f64) -> String {
+    if f.is_nan() {
+        return ".nan".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { ".inf" } else { "-.inf" }.to_string();
+    }
+
+    let s = f.to_string();
+    if !s.contains(['.', 'e', 'E']) {
+        format!("{}.0", s)
+    } else {
+        s
+    }
+}
+
+// 暗黙のキー（`key:`）として書けないキー
+fn is_complex_key(key: &YamlValue) -> bool {
+    matches!(key, YamlValue::Array(_) | YamlValue::Object(_) | YamlValue::Tagged(..))
+}
+
+// 制御文字を含む文字列はダブルクォートのエスケープが必要
+fn needs_escapes(s: &str) -> bool {
+    s.chars().any(|c| c.is_control())
+}
+
+fn double_quoted(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            '\u{1B}' => out.push_str("\\e"),
+            '\u{85}' => out.push_str("\\N"),
+            c if c.is_control() => out.push_str(&format!("\\x{:02X}", c as u32)),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// プレーンスカラーとして書くと意味が変わる文字列か
+fn needs_quotes(s: &str, context: Context) -> bool {
+    if s.is_empty() || s.trim() != s {
+        return true;
+    }
+
+    // 真偽値・数値・nullとして解釈される文字列（キーも同じように解釈される）。
+    // YAML 1.1 で読むツールのために `yes` や `0777` も引用符で囲む
+    if [Schema::Core, Schema::Yaml11]
+        .iter()
+        .any(|schema| !matches!(schema.resolve(s), YamlValue::String(_)))
+    {
+        return true;
+    }
+
+    let first = s.chars().next().unwrap_or(' ');
+    if matches!(
+        first,
+        '[' | ']' | '{' | '}' | ',' | '#' | '&' | '*' | '!' | '|' | '>' | '\'' | '"' | '%' | '@' | '`' | ':' | '?'
+    ) {
+        return true;
+    }
+    if s == "-" || s.starts_with("- ") || s.starts_with("---") || s.starts_with("...") {
+        return true;
+    }
+
+    if s.contains(": ") || s.ends_with(':') || s.contains(" #") {
+        return true;
+    }
+
+    match context {
+        Context::Block => false,
+        // フローマッピングのキーでも `<<` はマージキーになる
+        Context::Flow => s == "<<" || s.contains([',', '[', ']', '{', '}', ':', '#']),
+        // `<<` はマージキー、`-` で始まるキーはリストの項目と紛らわしい
+        Context::Key => s == "<<" || s.starts_with('-') || s.contains([':', '#']),
+    }
+}
+
+// リテラルブロックで正確に表現できる複数行の文字列か
+fn is_literal_block_safe(s: &str) -> bool {
+    let content = s.trim_end_matches('\n');
+
+    s.contains('\n')
+        && !content.is_empty()
+        && !content.starts_with([' ', '\t', '\n'])
+        && !s.chars().any(|c| c.is_control() && c != '\n')
+        && content.split('\n').all(|line| line.is_empty() || !line.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_yaml, yaml};
+    use crate::mapping::Mapping;
+
+    fn round_trip(value: &YamlValue, options: EmitterOptions) {
+        let text = Emitter::new(options).emit(value);
+        let reparsed = parse_yaml(&text).unwrap_or_else(|e| panic!("{}\n---\n{}", e, text));
+        assert_eq!(&reparsed, value, "emitted:\n{}", text);
+    }
+
+    fn sample() -> YamlValue {
+        yaml!({
+            "server": {
+                "host": "localhost",
+                "port": 8080,
+                "ratio": 1.0,
+                "tags": ["a", "true", "1.5"],
+            },
+            "users": [
+                {
+                    "name": "Alice: admin",
+                    "note": "line 1\nline 2\n",
+                    "groups": ["x"],
+                },
+                null,
+                [1, false],
+            ],
+            "empty": {},
+            "none": [],
+            "key: with colon": "",
+            "script": "echo a\n\n\n",
+            "quote": "it's",
+            "control": "\0\u{7}\u{1b}\u{85}\u{9f}",
+            "jobs": [
+                {
+                    "a_steps": ["build", ["- x", null]],
+                    "b_env": { "RUST_LOG": "debug" },
+                },
+                [[1, 2], "#not a comment"],
+            ],
+        })
+    }
+
+    #[test]
+    fn test_emit_simple_mapping() {
+        let mut map = Mapping::new();
+        map.insert("name".to_string(), YamlValue::String("John".to_string()));
+        map.insert("age".to_string(), YamlValue::Integer(30));
+        map.insert("ports".to_string(), YamlValue::Array(vec![YamlValue::Integer(80)]));
+
+        assert_eq!(
+            Emitter::default().emit(&YamlValue::Object(map)),
+            "name: John\nage: 30\nports:\n  - 80\n"
+        );
+    }
+
+    #[test]
+    fn test_emit_quotes_ambiguous_strings() {
+        let emitter = Emitter::default();
+        assert_eq!(emitter.emit(&YamlValue::String("true".to_string())), "\"true\"\n");
+        assert_eq!(emitter.emit(&YamlValue::String("42".to_string())), "\"42\"\n");
+        assert_eq!(emitter.emit(&YamlValue::String("~".to_string())), "\"~\"\n");
+        assert_eq!(emitter.emit(&YamlValue::String("- item".to_string())), "\"- item\"\n");
+        assert_eq!(emitter.emit(&YamlValue::String("plain text".to_string())), "plain text\n");
+        assert_eq!(emitter.emit(&YamlValue::Float(2.0)), "2.0\n");
+
+        // 他のスキーマで別の型になる文字列と特殊な浮動小数点数
+        assert_eq!(emitter.emit(&YamlValue::String("yes".to_string())), "\"yes\"\n");
+        assert_eq!(emitter.emit(&YamlValue::String("0x1F".to_string())), "\"0x1F\"\n");
+        assert_eq!(emitter.emit(&YamlValue::String("1:20".to_string())), "\"1:20\"\n");
+        assert_eq!(emitter.emit(&YamlValue::Float(f64::NEG_INFINITY)), "-.inf\n");
+        assert_eq!(emitter.emit(&YamlValue::Float(f64::NAN)), ".nan\n");
+    }
+
+    #[test]
+    fn test_emit_non_string_keys() {
+        let mut map = Mapping::new();
+        map.insert(200, YamlValue::from("OK"));
+        map.insert("200", YamlValue::from("text"));
+        map.insert(YamlValue::Null, YamlValue::Boolean(true));
+        map.insert(vec!["a", "b"], YamlValue::from(vec![1]));
+        map.insert(yaml!({"k": "v"}), YamlValue::Null);
+        let value = YamlValue::Object(map);
+
+        assert_eq!(
+            Emitter::default().emit(&value),
+            "200: OK\n\"200\": text\nnull: true\n? [a, b]\n:\n  - 1\n? {k: v}\n: null\n"
+        );
+        round_trip(&value, EmitterOptions::default());
+        round_trip(&yaml!([(value.clone()), {"x": (value.clone())}]), EmitterOptions {
+            indent: 4,
+            indent_sequences: false,
+            ..EmitterOptions::default()
+        });
+        round_trip(&yaml!({"list": [(value)]}), EmitterOptions {
+            max_flow_width: Some(80),
+            ..EmitterOptions::default()
+        });
+    }
+
+    #[test]
+    fn test_emit_quotes_ambiguous_keys() {
+        let value = yaml!({"<<": "x", "-x": 1, "a-b": 2});
+        assert_eq!(Emitter::default().emit(&value), "\"<<\": x\n\"-x\": 1\na-b: 2\n");
+        round_trip(&value, EmitterOptions::default());
+        round_trip(&yaml!([(value.clone()), {"k": [(value)]}]), EmitterOptions {
+            quote_style: QuoteStyle::Single,
+            ..EmitterOptions::default()
+        });
+
+        // 複合キーの中のフローマッピング
+        let mut inner = Mapping::new();
+        inner.insert(YamlValue::String("<<".to_string()), YamlValue::Null);
+        let mut outer = Mapping::new();
+        outer.insert(YamlValue::Object(inner), YamlValue::Null);
+        let value = YamlValue::Object(outer);
+        assert_eq!(Emitter::default().emit(&value), "? {\"<<\": null}\n: null\n");
+        round_trip(&value, EmitterOptions::default());
+        round_trip(&yaml!({"k": {"<<": 1}}), EmitterOptions { max_flow_width: Some(80), ..EmitterOptions::default() });
+    }
+
+    #[test]
+    fn test_emit_hash_in_plain_scalars() {
+        // 空白の後にない '#' はコメントにならないので引用符は不要
+        let value = yaml!({
+            "a": "b#c",
+            "url": "http://x/#frag",
+            "k#1": ["#x", "y #z", "c#"],
+        });
+
+        assert_eq!(
+            Emitter::default().emit(&value),
+            "a: b#c
+url: http://x/#frag
+\"k#1\":\n  - \"#x\"\n  - \"y #z\"\n  - c#\n"
+        );
+        round_trip(&value, EmitterOptions::default());
+        round_trip(&value, EmitterOptions { max_flow_width: Some(80), ..EmitterOptions::default() });
+    }
+
+    #[test]
+    fn test_emit_round_trip() {
+        round_trip(&sample(), EmitterOptions::default());
+    }
+
+    #[test]
+    fn test_emit_round_trip_with_options() {
+        round_trip(&sample(), EmitterOptions {
+            indent: 4,
+            indent_sequences: false,
+            quote_style: QuoteStyle::Single,
+            max_flow_width: Some(40),
+            literal_block_strings: false,
+        });
+        round_trip(&sample(), EmitterOptions {
+            indent_sequences: false,
+            ..EmitterOptions::default()
+        });
+        round_trip(&sample(), EmitterOptions {
+            quote_style: QuoteStyle::Double,
+            max_flow_width: Some(80),
+            ..EmitterOptions::default()
+        });
+    }
+
+    #[test]
+    fn test_emit_tagged_values() {
+        let tagged = |tag: &str, value| YamlValue::Tagged(tag.to_string(), Box::new(value));
+        let value = yaml!({
+            "ref": (tagged("!Ref", yaml!("bucket"))),
+            "sub": (tagged("!Sub", yaml!(["a", {"b": 1}]))),
+            "map": (tagged("!Env", yaml!({"k": "v"}))),
+            "empty": (tagged("!Ref", YamlValue::Null)),
+            "text": (tagged("tag:yaml.org,2002:binary", yaml!("aGk=\n"))),
+            "uri": (tagged("tag:example.com,2000:app", yaml!("5"))),
+            "items": [(tagged("!Item", yaml!({"id": 1}))), (tagged("!Flag", YamlValue::Null))],
+        });
+
+        assert_eq!(
+            Emitter::default().emit(&value),
+            "ref: !Ref bucket\n\
+             sub: !Sub\n  - a\n  - b: 1\n\
+             map: !Env\n  k: v\n\
+             empty: !Ref\n\
+             text: !!binary |\n  aGk=\n\
+             uri: !<tag:example.com,2000:app> \"5\"\n\
+             items:\n  - !Item\n    id: 1\n  - !Flag\n"
+        );
+        round_trip(&value, EmitterOptions::default());
+        round_trip(&value, EmitterOptions {
+            indent: 4,
+            indent_sequences: false,
+            max_flow_width: Some(80),
+            ..EmitterOptions::default()
+        });
+        assert_eq!(Emitter::default().emit(&tagged("!Root", yaml!([1]))), "!Root\n- 1\n");
+
+        // 短縮形で書けないタグは逐語的に書く
+        let verbatim = yaml!([
+            (tagged("!e!", yaml!("x"))),
+            (tagged("!a,b", yaml!("y"))),
+            (tagged("tag:yaml.org,2002:a!b", yaml!("z"))),
+        ]);
+        assert_eq!(
+            Emitter::default().emit(&verbatim),
+            "- !<!e!> x\n- !<!a,b> y\n- !<tag:yaml.org,2002:a!b> z\n"
+        );
+        round_trip(&verbatim, EmitterOptions::default());
+        round_trip(&verbatim, EmitterOptions { max_flow_width: Some(80), ..EmitterOptions::default() });
+
+        // URI に使えない文字は短縮形の % エスケープで書く
+        let escaped = yaml!([
+            (tagged("!\"", yaml!("x"))),
+            (tagged("!a>b", yaml!("y"))),
+            (tagged("tag:yaml.org,2002:a b", yaml!("z"))),
+        ]);
+        assert_eq!(
+            Emitter::default().emit(&escaped),
+            "- !%22 x\n- !a%3Eb y\n- !!a%20b z\n"
+        );
+        round_trip(&escaped, EmitterOptions::default());
+    }
+
+    #[test]
+    fn test_emit_flow_threshold() {
+        let value = YamlValue::Array(vec![YamlValue::Integer(1), YamlValue::String("a, b".to_string())]);
+        let options = EmitterOptions {
+            max_flow_width: Some(20),
+            ..EmitterOptions::default()
+        };
+        assert_eq!(Emitter::new(options).emit(&value), "[1, \"a, b\"]\n");
+        assert_eq!(Emitter::default().emit(&value), "- 1\n- a, b\n");
+    }
+}
@@ -1,28 +1,266 @@
+use crate::token::Span;
 use std::fmt;
+use std::io;
 
 #[derive(Debug)]
 pub enum YamlError {
-    ParseError(String),
-    IndentationError(String),
-    InvalidValue(String),
-    UnexpectedChar { char: char, line: usize, column: usize },
-    UnexpectedEof,
+    ParseError { message: String, span: Span },
+    IndentationError { message: String, span: Span },
+    InvalidValue {
+        message: String,
+        path: String,
+        span: Option<Span>,
+    },
+    UnexpectedChar { char: char, span: Span },
+    UnexpectedEof { span: Span },
+    /// Reading the input failed, or it was not valid UTF-8
+    Io(io::Error),
+}
+
+impl YamlError {
+    /// Create an `InvalidValue` error for the value being deserialized
+    ///
+    /// The path and source position are filled in as the error propagates
+    /// through `extract_field`, sequences and `parse_yaml_to`.
+    pub fn invalid_value(message: impl Into<String>) -> Self {
+        YamlError::InvalidValue {
+            message: message.into(),
+            path: String::new(),
+            span: None,
+        }
+    }
+
+    /// Path of the offending node for `InvalidValue` errors, e.g. `database.replicas[2].port`
+    ///
+    /// Returns `None` for other errors and `Some("")` for the root value.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            YamlError::InvalidValue { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Prefix the path of an `InvalidValue` error with a mapping key
+    pub fn in_field(self, key: &str) -> Self {
+        self.prefix_path(|prefix| push_key(prefix, key))
+    }
+
+    /// Prefix the path of an `InvalidValue` error with a sequence index
+    pub fn at_index(self, index: usize) -> Self {
+        self.prefix_path(|prefix| push_index(prefix, index))
+    }
+
+    fn prefix_path(self, segment: impl FnOnce(&mut String)) -> Self {
+        match self {
+            YamlError::InvalidValue { message, path, span } => {
+                let mut prefix = String::new();
+                segment(&mut prefix);
+                if !path.is_empty() && !path.starts_with('[') {
+                    prefix.push('.');
+                }
+                prefix.push_str(&path);
+                YamlError::InvalidValue { message, path: prefix, span }
+            }
+            other => other,
+        }
+    }
+
+    /// Source position of the error, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            YamlError::ParseError { span, .. }
+            | YamlError::IndentationError { span, .. }
+            | YamlError::UnexpectedChar { span, .. }
+            | YamlError::UnexpectedEof { span } => Some(*span),
+            YamlError::InvalidValue { span, .. } => *span,
+            YamlError::Io(_) => None,
+        }
+    }
+
+    /// Error message without the position prefix
+    pub fn message(&self) -> String {
+        match self {
+            YamlError::ParseError { message, .. }
+            | YamlError::IndentationError { message, .. }
+            | YamlError::InvalidValue { message, .. } => message.clone(),
+            YamlError::UnexpectedChar { char, .. } => format!("Unexpected character '{}'", char.escape_debug()),
+            YamlError::UnexpectedEof { .. } => "Unexpected end of file".to_string(),
+            YamlError::Io(error) => error.to_string(),
+        }
+    }
+
+    /// Render the error with the offending source line and a `^` marker
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The YAML text that produced the error
+    ///
+    /// # Returns
+    ///
+    /// A multi-line diagnostic in the style of rustc
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yaml_parser::parse_yaml;
+    ///
+    /// let source = "name: test\nport 8080\n";
+    /// let error = parse_yaml(source).unwrap_err();
+    /// println!("{}", error.render(source));
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        self.render_snippet(None, source)
+    }
+
+    /// Same as [`YamlError::render`], but labels the location with a file name
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - Name of the source, usually a file path
+    /// * `source` - The YAML text that produced the error
+    pub fn render_named(&self, origin: &str, source: &str) -> String {
+        self.render_snippet(Some(origin), source)
+    }
+
+    fn render_snippet(&self, origin: Option<&str>, source: &str) -> String {
+        let mut out = match self.path() {
+            Some(path) if !path.is_empty() => format!("error: {}: {}\n", path, self.message()),
+            _ => format!("error: {}\n", self.message()),
+        };
+
+        let span = match self.span() {
+            Some(span) if span.line > 0 => span,
+            _ => return out,
+        };
+
+        match origin {
+            Some(origin) => out.push_str(&format!(" --> {}:{}:{}\n", origin, span.line, span.column)),
+            None => out.push_str(&format!(" --> line {}, column {}\n", span.line, span.column)),
+        }
+
+        // 行末（EOF）を指す場合も最終行を表示する
+        let text = source.lines().nth(span.line - 1).unwrap_or("");
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        // タブは幅を揃えるためにそのまま残す
+        let padding: String = text
+            .chars()
+            .take(span.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        out.push_str(&format!("{} |\n", gutter));
+        out.push_str(&format!("{} | {}\n", number, text.trim_end_matches('\r')));
+        out.push_str(&format!("{} | {}^\n", gutter, padding));
+        out
+    }
 }
 
 impl fmt::Display for YamlError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            YamlError::ParseError(msg) => write!(f, "Parse error: {}", msg),
-            YamlError::IndentationError(msg) => write!(f, "Indentation error: {}", msg),
-            YamlError::InvalidValue(msg) => write!(f, "Invalid Value error: {}", msg),
-            YamlError::UnexpectedChar { char, line, column } => {
-                write!(f, "Unexpected character '{}' at line {}, column {}", char, line, column)
+            YamlError::ParseError { message, span } => {
+                write!(f, "Parse error at line {}, column {}: {}", span.line, span.column, message)
+            }
+            YamlError::IndentationError { message, span } => {
+                write!(f, "Indentation error at line {}, column {}: {}", span.line, span.column, message)
+            }
+            YamlError::InvalidValue { message, path, span } => {
+                write!(f, "Invalid Value error")?;
+                if !path.is_empty() {
+                    write!(f, " at {}", path)?;
+                }
+                if let Some(span) = span {
+                    write!(f, " (line {}, column {})", span.line, span.column)?;
+                }
+                write!(f, ": {}", message)
             }
-            YamlError::UnexpectedEof => write!(f, "Unexpected end of file"),
+            YamlError::UnexpectedChar { char, span } => {
+                write!(f, "Unexpected character '{}' at line {}, column {}", char.escape_debug(), span.line, span.column)
+            }
+            YamlError::UnexpectedEof { span } => {
+                write!(f, "Unexpected end of file at line {}, column {}", span.line, span.column)
+            }
+            YamlError::Io(error) => write!(f, "I/O error: {}", error),
         }
     }
 }
 
-impl std::error::Error for YamlError {}
+impl std::error::Error for YamlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            YamlError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
 
-pub type Result<T> = std::result::Result<T, YamlError>;
\ No newline at end of file
+// パスにマッピングキーを追加（`.` や `[` を含むキーは引用符で囲む）
+pub(crate) fn push_key(path: &mut String, key: &str) {
+    if !path.is_empty() {
+        path.push('.');
+    }
+    if key.is_empty() || key.contains(['.', '[', ']', '"', ' ']) {
+        path.push_str(&format!("{:?}", key));
+    } else {
+        path.push_str(key);
+    }
+}
+
+// パスにシーケンスのインデックスを追加
+pub(crate) fn push_index(path: &mut String, index: usize) {
+    path.push_str(&format!("[{}]", index));
+}
+
+pub type Result<T> = std::result::Result<T, YamlError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_includes_position() {
+        let error = YamlError::ParseError {
+            message: "Expected ':' after key".to_string(),
+            span: Span { line: 2, column: 6, offset: 16 },
+        };
+        assert_eq!(error.to_string(), "Parse error at line 2, column 6: Expected ':' after key");
+    }
+
+    #[test]
+    fn test_render_snippet() {
+        let source = "name: test\nport 8080\n";
+        let error = YamlError::UnexpectedChar {
+            char: '@',
+            span: Span { line: 2, column: 6, offset: 16 },
+        };
+        assert_eq!(
+            error.render_named("config.yaml", source),
+            "error: Unexpected character '@'\n --> config.yaml:2:6\n  |\n2 | port 8080\n  |      ^\n"
+        );
+    }
+
+    #[test]
+    fn test_render_without_span() {
+        let error = YamlError::invalid_value("Missing field: port");
+        assert_eq!(error.render("port: 1"), "error: Missing field: port\n");
+    }
+
+    #[test]
+    fn test_invalid_value_path() {
+        let error = YamlError::invalid_value("Expected integer, found String(\"abc\")")
+            .in_field("port")
+            .at_index(2)
+            .in_field("replicas")
+            .in_field("database");
+        assert_eq!(error.path(), Some("database.replicas[2].port"));
+        assert_eq!(
+            error.to_string(),
+            "Invalid Value error at database.replicas[2].port: Expected integer, found String(\"abc\")"
+        );
+
+        let error = YamlError::invalid_value("Missing field: x").in_field("a.b").at_index(0);
+        assert_eq!(error.path(), Some("[0].\"a.b\""));
+    }
+}
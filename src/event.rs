@@ -0,0 +1,1307 @@
+//! Pull-style event parser
+//!
+//! [`Events`] turns a token stream into a flat sequence of [`Event`]s, in the
+//! style of libyaml: stream and document boundaries, the start and end of
+//! each mapping and sequence, scalars and aliases. No tree is built, so a
+//! consumer can process documents of any size with memory proportional to
+//! their nesting depth.
+//!
+//! ```rust
+//! use yaml_parser::{parse_yaml_events, Event, YamlValueRef};
+//!
+//! let events: Vec<Event> = parse_yaml_events("a: [1, 2]").collect::<Result<_, _>>().unwrap();
+//! assert_eq!(events[2], Event::MappingStart { anchor: None, tag: None });
+//! assert_eq!(events[3], Event::Scalar { value: YamlValueRef::String("a".into()), anchor: None, tag: None });
+//! ```
+//!
+//! Scalars borrow their text from the input where possible.
+//!
+//! Anchors are reported on the node they label; resolving aliases and merge
+//! keys is left to the consumer (see [`Parser`](crate::Parser)).
+//!
+//! Tags are reported with their handle expanded (`!!binary` becomes
+//! `tag:yaml.org,2002:binary`, `%TAG` directives apply to the document that
+//! follows them). The standard scalar tags `!!str`, `!!int`, `!!float`,
+//! `!!bool` and `!!null`, as well as `!!map` and `!!seq` on collections, are
+//! applied while parsing and not reported:
+//!
+//! ```rust
+//! use yaml_parser::{parse_yaml_events, Event, YamlValueRef};
+//!
+//! let events: Vec<Event> = parse_yaml_events("- !!str 123\n- !Ref bucket").collect::<Result<_, _>>().unwrap();
+//! assert_eq!(events[3], Event::Scalar { value: YamlValueRef::String("123".into()), anchor: None, tag: None });
+//! assert_eq!(events[4], Event::Scalar {
+//!     value: YamlValueRef::String("bucket".into()),
+//!     anchor: None,
+//!     tag: Some("!Ref".to_string()),
+//! });
+//! ```
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use crate::error::{Result, YamlError};
+use crate::lexer::Lexer;
+use crate::schema::{Schema, YAML_TAG_PREFIX};
+use crate::token::{Span, SpannedToken, Token};
+use crate::value::YamlValueRef;
+
+/// A parsing event
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    StreamStart,
+    StreamEnd,
+    /// `explicit` is true when the document starts with `---`
+    DocumentStart { explicit: bool },
+    /// `explicit` is true when the document ends with `...`
+    DocumentEnd { explicit: bool },
+    MappingStart { anchor: Option<String>, tag: Option<String> },
+    MappingEnd,
+    SequenceStart { anchor: Option<String>, tag: Option<String> },
+    SequenceEnd,
+    /// A scalar node. Plain mapping keys are resolved like other plain
+    /// scalars (`200:` is an integer key); a key after `?` can be any node.
+    /// The merge key `<<` has the tag `tag:yaml.org,2002:merge`.
+    Scalar { value: YamlValueRef<'a>, anchor: Option<String>, tag: Option<String> },
+    /// `*name`
+    Alias(String),
+}
+
+/// An event together with the position of the node or marker it describes
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedEvent<'a> {
+    pub event: Event<'a>,
+    pub span: Span,
+}
+
+// パースするノードの文脈（再帰下降パーサーの各関数に対応する）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Node {
+    Value,         // 任意の値
+    MappingValue,  // キーの後の値（同じ行、または次の行のブロック）
+    ListItemValue, // '-' の後の値
+    FlowEntry,     // フローコレクション内の値
+    BlockSequence, // キーと同じ列から始まるシーケンス
+}
+
+// パーサーの状態。スタックの先頭が次に処理する状態
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    StreamStart,
+    DocumentStart,
+    DocumentBody,
+    DocumentMappingKey,
+    DocumentValueEnd,
+    DocumentEnd,
+    Node(Node),
+    Null(Span),
+    ConsumeDedent,
+    MappingKey { initial_indent: usize },
+    SequenceEntry { initial_indent: usize },
+    SequenceEntryEnd { initial_indent: usize },
+    ItemMappingKey { item_indent: usize, indented: bool },
+    ItemMappingEntryEnd { item_indent: usize, indented: bool },
+    CompactSequenceEntry { indented: bool },
+    CompactSequenceEntryEnd { indented: bool },
+    FlowSequenceEntry,
+    FlowSequenceEntryEnd,
+    FlowSinglePairEnd,
+    FlowMappingEntry,
+    FlowMappingEntryEnd,
+    FlowPair,
+    FlowPairValue,
+    ExplicitValue,
+    ItemExplicitValue { item_indent: usize, indented: bool },
+}
+
+/// Iterator over the parsing events of a token stream
+///
+/// Created by [`parse_yaml_events`](crate::parse_yaml_events),
+/// [`Events::from_lexer`] or [`Events::with_spans`]. Tokens are pulled from
+/// the source only as far as needed, so memory use does not grow with the
+/// length of the input. Iteration stops after the first error.
+pub struct Events<'a> {
+    tokens: Box<dyn Iterator<Item = Result<SpannedToken<'a>>> + 'a>,
+    lookahead: VecDeque<SpannedToken<'a>>, // 読み込み済みで未消費のトークン
+    last_span: Span,                  // 最後に読み込んだトークンの位置
+    indent_stack: Vec<usize>,         // 消費した Indent / Dedent から求めたインデント
+    states: Vec<State>,
+    pending_anchor: Option<String>,   // 次のノードに付けるアンカー
+    pending_tag: Option<String>,      // 次のノードに付ける展開済みのタグ
+    tag_handles: Vec<(String, String)>, // 現在のドキュメントの %TAG（ハンドル, 接頭辞）
+    error: Option<YamlError>,         // トークン化のエラー
+    schema: Schema,                   // プレーンスカラーの型解決
+}
+
+impl<'a> Events<'a> {
+    pub fn new(tokens: Vec<Token<'a>>) -> Self {
+        let tokens = tokens
+            .into_iter()
+            .map(|token| SpannedToken { token, span: Span::default() })
+            .collect();
+        Self::with_spans(tokens)
+    }
+
+    /// Create an event parser from tokens produced by
+    /// [`Lexer::tokenize_with_spans`](crate::Lexer::tokenize_with_spans), so
+    /// that events and errors carry their source position.
+    pub fn with_spans(tokens: Vec<SpannedToken<'a>>) -> Self {
+        Self::from_tokens(tokens.into_iter().map(Ok))
+    }
+
+    /// Create an event parser that pulls tokens from `lexer` on demand
+    pub fn from_lexer(lexer: Lexer<'a>) -> Self {
+        Self::from_tokens(lexer)
+    }
+
+    fn from_tokens(tokens: impl Iterator<Item = Result<SpannedToken<'a>>> + 'a) -> Self {
+        Self {
+            tokens: Box::new(tokens),
+            lookahead: VecDeque::new(),
+            last_span: Span::default(),
+            indent_stack: vec![0],
+            states: vec![State::StreamStart],
+            pending_anchor: None,
+            pending_tag: None,
+            tag_handles: Vec::new(),
+            error: None,
+            schema: Schema::default(),
+        }
+    }
+
+    /// Resolve plain scalars with `schema` instead of the YAML 1.2 core schema
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Return the next event together with its source position
+    pub fn next_spanned(&mut self) -> Option<Result<SpannedEvent<'a>>> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+
+        while let Some(state) = self.states.pop() {
+            let result = self.step(state);
+
+            // トークン化のエラーは、それによって生じた構文エラーより優先する
+            let result = match self.error.take() {
+                Some(error) => Err(error),
+                None => result,
+            };
+
+            match result {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => {}
+                Err(error) => {
+                    // エラーの後はイベントを返さない
+                    self.states.clear();
+                    return Some(Err(error));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Position of the next token; after the last event, the end of the input
+    pub fn current_span(&mut self) -> Span {
+        self.fill(1);
+        self.lookahead.front().map_or(self.last_span, |spanned| spanned.span)
+    }
+
+    // 先読みバッファに n 個のトークンを読み込む（入力の終わりではそれ以下）
+    fn fill(&mut self, n: usize) {
+        while self.lookahead.len() < n {
+            match self.tokens.next() {
+                Some(Ok(spanned)) => {
+                    self.last_span = spanned.span;
+                    self.lookahead.push_back(spanned);
+                }
+                Some(Err(error)) => {
+                    // 以降は入力の終わりとして扱い、next_spanned でエラーを返す
+                    self.error = Some(error);
+                    self.tokens = Box::new(std::iter::empty());
+                    break;
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn is_at_end(&mut self) -> bool {
+        matches!(self.peek(), Token::Eof)
+    }
+
+    fn peek(&mut self) -> &Token<'a> {
+        self.fill(1);
+        self.lookahead.front().map_or(&Token::Eof, |spanned| &spanned.token)
+    }
+
+    fn peek_next(&mut self) -> Option<&Token<'a>> {
+        self.fill(2);
+        self.lookahead.get(1).map(|spanned| &spanned.token)
+    }
+
+    fn advance(&mut self) {
+        if self.is_at_end() {
+            return;
+        }
+
+        // インデントの状態を追跡する
+        match self.lookahead.pop_front().map(|spanned| spanned.token) {
+            Some(Token::Indent(level)) => self.indent_stack.push(level),
+            Some(Token::Dedent(_)) if self.indent_stack.len() > 1 => {
+                self.indent_stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while matches!(self.peek(), Token::Newline | Token::Comment(_)) {
+            self.advance();
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>) -> YamlError {
+        YamlError::ParseError {
+            message: message.into(),
+            span: self.current_span(),
+        }
+    }
+
+    // 現在のトークンが予期しないものである場合のエラー
+    fn unexpected_token(&mut self, context: &str) -> YamlError {
+        let span = self.current_span();
+        match self.peek() {
+            Token::Eof => YamlError::UnexpectedEof { span },
+            Token::Indent(_) | Token::Dedent(_) => YamlError::IndentationError {
+                message: format!("Unexpected indentation {}", context),
+                span,
+            },
+            other => YamlError::ParseError {
+                message: format!("Unexpected token {}: {:?}", context, other),
+                span,
+            },
+        }
+    }
+
+    fn emit(&self, event: Event<'a>, span: Span) -> Result<Option<SpannedEvent<'a>>> {
+        Ok(Some(SpannedEvent { event, span }))
+    }
+
+    // ノードの開始イベント（保留中のアンカーとタグを付ける）
+    fn scalar(&mut self, value: YamlValueRef<'a>, span: Span) -> Result<Option<SpannedEvent<'a>>> {
+        let anchor = self.pending_anchor.take();
+        let (value, tag) = match self.pending_tag.take() {
+            Some(tag) => self.tag_scalar(tag, value, span)?,
+            None => (value, None),
+        };
+        self.emit(Event::Scalar { value, anchor, tag }, span)
+    }
+
+    // プレーンスカラー。`!` や標準タグ（!!int など）が付いていなければ
+    // スキーマで型を決める
+    fn plain_scalar(&mut self, text: Cow<'a, str>, span: Span) -> Result<Option<SpannedEvent<'a>>> {
+        let value = match &self.pending_tag {
+            Some(tag) if tag == "!" || tag.starts_with(YAML_TAG_PREFIX) => YamlValueRef::String(text),
+            _ => self.schema.resolve_borrowed(text),
+        };
+        self.scalar(value, span)
+    }
+
+    fn mapping_start(&mut self, span: Span) -> Result<Option<SpannedEvent<'a>>> {
+        let anchor = self.pending_anchor.take();
+        let tag = self.collection_tag("map", span)?;
+        self.emit(Event::MappingStart { anchor, tag }, span)
+    }
+
+    fn sequence_start(&mut self, span: Span) -> Result<Option<SpannedEvent<'a>>> {
+        let anchor = self.pending_anchor.take();
+        let tag = self.collection_tag("seq", span)?;
+        self.emit(Event::SequenceStart { anchor, tag }, span)
+    }
+
+    // マッピングのキー（アンカーは付かない）。引用符のないキーはスキーマで
+    // 型を決め（`200:` は整数のキー）、`<<` には !!merge タグを付ける
+    fn key(&self, key: Cow<'a, str>, quoted: bool, span: Span) -> Result<Option<SpannedEvent<'a>>> {
+        let event = if quoted {
+            Event::Scalar { value: YamlValueRef::String(key), anchor: None, tag: None }
+        } else if key == "<<" {
+            let tag = Some(format!("{}merge", YAML_TAG_PREFIX));
+            Event::Scalar { value: YamlValueRef::String(key), anchor: None, tag }
+        } else {
+            Event::Scalar { value: self.schema.resolve_borrowed(key), anchor: None, tag: None }
+        };
+        self.emit(event, span)
+    }
+
+    // ブロックマッピングのキーが続くか（`key:`、`"key":`、`? key`）
+    fn at_block_key(&mut self) -> bool {
+        matches!(self.peek(), Token::Key(_) | Token::QuotedKey(_) | Token::ExplicitKey)
+    }
+
+    // ブロックマッピングのエントリを読み始める。`next` はエントリの後の状態
+    fn block_entry(&mut self, next: State) -> Result<Option<SpannedEvent<'a>>> {
+        // `? key` のキーは `-` の後と同じように任意のノードを置ける
+        if matches!(self.peek(), Token::ExplicitKey) {
+            self.advance();
+            self.states.push(next);
+            self.states.push(State::ExplicitValue);
+            self.states.push(State::Node(Node::ListItemValue));
+            return Ok(None);
+        }
+
+        let (key, quoted, span) = self.read_key()?;
+        self.states.push(next);
+        self.states.push(State::Node(Node::MappingValue));
+        self.key(key, quoted, span)
+    }
+
+    // スカラーにタグを適用する。`!` と標準のスカラー型のタグは値に反映して
+    // 取り除き、それ以外のタグはイベントに残す
+    fn tag_scalar(
+        &self,
+        tag: String,
+        value: YamlValueRef<'a>,
+        span: Span,
+    ) -> Result<(YamlValueRef<'a>, Option<String>)> {
+        let name = match tag.strip_prefix(YAML_TAG_PREFIX) {
+            Some(name) => name,
+            None if tag == "!" => "str",
+            None => return Ok((value, Some(tag))),
+        };
+
+        // 空のノードは空文字列として解決する（標準タグの付いたプレーンスカラーは文字列のまま届く）
+        let text = match value {
+            YamlValueRef::String(text) => text,
+            YamlValueRef::Null => Cow::Borrowed(""),
+            other => return Ok((other, Some(tag))),
+        };
+
+        match name {
+            "str" | "null" | "bool" | "int" | "float" => {
+                match self.schema.resolve_tagged(name, text.clone()) {
+                    Some(value) => Ok((value, None)),
+                    None => Err(YamlError::ParseError {
+                        message: format!("Invalid value for !!{}: {:?}", name, text),
+                        span,
+                    }),
+                }
+            }
+            "map" | "seq" => Err(YamlError::ParseError {
+                message: format!("Tag !!{} cannot be applied to a scalar", name),
+                span,
+            }),
+            _ => Ok((YamlValueRef::String(text), Some(tag))),
+        }
+    }
+
+    // コレクションのタグ。`!` と種類の合う !!map / !!seq は取り除く
+    fn collection_tag(&mut self, kind: &str, span: Span) -> Result<Option<String>> {
+        let tag = match self.pending_tag.take() {
+            Some(tag) if tag != "!" => tag,
+            _ => return Ok(None),
+        };
+
+        match tag.strip_prefix(YAML_TAG_PREFIX) {
+            Some(name) if name == kind => Ok(None),
+            Some(name @ ("str" | "null" | "bool" | "int" | "float" | "map" | "seq")) => {
+                Err(YamlError::ParseError {
+                    message: format!(
+                        "Tag !!{} cannot be applied to a {}",
+                        name,
+                        if kind == "map" { "mapping" } else { "sequence" }
+                    ),
+                    span,
+                })
+            }
+            _ => Ok(Some(tag)),
+        }
+    }
+
+    // タグのハンドルを %TAG ディレクティブ（なければ既定の `!` と `!!`）で展開する
+    fn expand_tag(&self, tag: &str, span: Span) -> Result<String> {
+        if let Some(verbatim) = tag.strip_prefix("!<") {
+            return Ok(verbatim.trim_end_matches('>').to_string());
+        }
+        if tag == "!" {
+            return Ok(tag.to_string());
+        }
+
+        // `!e!suffix` と `!!suffix` は名前付きハンドル、それ以外は `!suffix`
+        let (handle, suffix) = match tag[1..].find('!') {
+            Some(i) => tag.split_at(i + 2),
+            None => tag.split_at(1),
+        };
+        if suffix.is_empty() {
+            return Err(YamlError::ParseError {
+                message: format!("Missing tag suffix after {}", handle),
+                span,
+            });
+        }
+
+        let prefix = match self.tag_handles.iter().find(|(h, _)| h == handle) {
+            Some((_, prefix)) => prefix.as_str(),
+            None if handle == "!" => "!",
+            None if handle == "!!" => YAML_TAG_PREFIX,
+            None => {
+                return Err(YamlError::ParseError {
+                    message: format!("Undefined tag handle: {}", handle),
+                    span,
+                });
+            }
+        };
+        Ok(format!("{}{}", prefix, percent_decode(suffix)))
+    }
+
+    // `%TAG handle prefix` を登録する。%YAML とその他のディレクティブは無視する
+    fn read_directive(&mut self, directive: &str, span: Span) -> Result<()> {
+        let mut parts = directive.split_whitespace();
+        if parts.next() != Some("TAG") {
+            return Ok(());
+        }
+
+        let (handle, prefix) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(handle), Some(prefix), None) if is_tag_handle(handle) => (handle, prefix),
+            _ => {
+                return Err(YamlError::ParseError {
+                    message: format!("Invalid directive: %{}", directive),
+                    span,
+                });
+            }
+        };
+
+        if self.tag_handles.iter().any(|(h, _)| h == handle) {
+            return Err(YamlError::ParseError {
+                message: format!("Duplicate %TAG directive for {}", handle),
+                span,
+            });
+        }
+        self.tag_handles.push((handle.to_string(), prefix.to_string()));
+        Ok(())
+    }
+
+    // `key:` を読み、キーの文字列と引用符の有無を返す
+    fn read_key(&mut self) -> Result<(Cow<'a, str>, bool, Span)> {
+        let span = self.current_span();
+        let (key, quoted) = match self.peek() {
+            Token::Key(key) => (key.clone(), false),
+            Token::QuotedKey(key) => (key.clone(), true),
+            _ => return Err(self.unexpected_token("where a key was expected")),
+        };
+        self.advance();
+
+        if !matches!(self.peek(), Token::Colon) {
+            return Err(self.error("Expected ':' after key"));
+        }
+        self.advance();
+        Ok((key, quoted, span))
+    }
+
+    fn step(&mut self, state: State) -> Result<Option<SpannedEvent<'a>>> {
+        match state {
+            State::StreamStart => {
+                self.states.push(State::DocumentStart);
+                let span = self.current_span();
+                self.emit(Event::StreamStart, span)
+            }
+
+            State::DocumentStart => {
+                // ドキュメント間の改行・コメント・インデント解除・`...` をスキップ
+                while matches!(self.peek(), Token::Newline | Token::Comment(_) | Token::Dedent(_) | Token::DocumentEnd) {
+                    self.advance();
+                }
+
+                // ディレクティブは次のドキュメントにのみ適用される
+                self.tag_handles.clear();
+                let mut has_directives = false;
+                while let Token::Directive(directive) = self.peek() {
+                    let directive = directive.clone();
+                    let span = self.current_span();
+                    self.read_directive(&directive, span)?;
+                    self.advance();
+                    self.skip_newlines();
+                    has_directives = true;
+                }
+
+                let span = self.current_span();
+                let explicit = match self.peek() {
+                    Token::DocumentStart => {
+                        self.advance();
+                        self.skip_newlines();
+                        true
+                    }
+                    _ if has_directives => return Err(self.error("Expected '---' after directives")),
+                    Token::Eof => return self.emit(Event::StreamEnd, span),
+                    _ => false,
+                };
+
+                self.states.push(State::DocumentEnd);
+                self.states.push(State::DocumentBody);
+                self.emit(Event::DocumentStart { explicit }, span)
+            }
+
+            State::DocumentBody => {
+                // 空のドキュメント（`---` の直後に次の境界）はnull
+                if self.is_at_document_end() {
+                    let span = self.current_span();
+                    return self.scalar(YamlValueRef::Null, span);
+                }
+
+                self.skip_newlines();
+
+                // トップレベルで複数のキーバリューペアがある場合はオブジェクトとして扱う
+                if self.at_block_key() {
+                    self.states.push(State::DocumentMappingKey);
+                    let span = self.current_span();
+                    self.mapping_start(span)
+                } else {
+                    // 単一の値またはリスト
+                    self.states.push(State::DocumentValueEnd);
+                    self.states.push(State::Node(Node::Value));
+                    Ok(None)
+                }
+            }
+
+            State::DocumentMappingKey => {
+                self.skip_newlines();
+                if !self.at_block_key() {
+                    let span = self.current_span();
+                    return self.emit(Event::MappingEnd, span);
+                }
+
+                self.block_entry(State::DocumentMappingKey)
+            }
+
+            State::DocumentValueEnd => {
+                self.skip_newlines();
+
+                // Dedentトークンをスキップ
+                while matches!(self.peek(), Token::Dedent(_)) {
+                    self.advance();
+                }
+
+                if !self.is_at_document_end() {
+                    return Err(self.unexpected_token("after document"));
+                }
+                Ok(None)
+            }
+
+            State::DocumentEnd => {
+                self.skip_newlines();
+                while matches!(self.peek(), Token::Dedent(_)) {
+                    self.advance();
+                }
+
+                let span = self.current_span();
+                let explicit = match self.peek() {
+                    Token::DocumentEnd => {
+                        self.advance();
+                        true
+                    }
+                    Token::DocumentStart | Token::Eof => false,
+                    _ => return Err(self.unexpected_token("after document")),
+                };
+
+                self.states.push(State::DocumentStart);
+                self.emit(Event::DocumentEnd { explicit }, span)
+            }
+
+            State::Node(node) => self.parse_node(node),
+
+            State::Null(span) => self.scalar(YamlValueRef::Null, span),
+
+            State::ConsumeDedent => {
+                // 次の行に書いたスカラー（`key:\n  value`）の後には改行が残っている
+                self.skip_newlines();
+                if matches!(self.peek(), Token::Dedent(_)) {
+                    self.advance();
+                }
+                Ok(None)
+            }
+
+            State::MappingKey { initial_indent } => {
+                self.skip_newlines();
+
+                // Check for dedent or end, and that we're at the same indent level
+                if matches!(self.peek(), Token::Dedent(_) | Token::Eof)
+                    || self.get_current_indent() < initial_indent
+                    || !self.at_block_key()
+                {
+                    let span = self.current_span();
+                    return self.emit(Event::MappingEnd, span);
+                }
+
+                self.block_entry(State::MappingKey { initial_indent })
+            }
+
+            State::SequenceEntry { initial_indent } => {
+                if !matches!(self.peek(), Token::ListItem) {
+                    let span = self.current_span();
+                    return self.emit(Event::SequenceEnd, span);
+                }
+
+                self.advance(); // consume '-'
+                self.states.push(State::SequenceEntryEnd { initial_indent });
+                self.states.push(State::Node(Node::ListItemValue));
+                Ok(None)
+            }
+
+            State::SequenceEntryEnd { initial_indent } => {
+                self.skip_newlines();
+
+                // 配列のレベルより深いDedentのみスキップ
+                while let Token::Dedent(level) = self.peek() {
+                    if *level > initial_indent {
+                        self.advance();
+                    } else {
+                        break;
+                    }
+                }
+
+                // Check if we're still at the same indent level
+                if self.get_current_indent() < initial_indent || self.is_at_end() {
+                    let span = self.current_span();
+                    return self.emit(Event::SequenceEnd, span);
+                }
+
+                self.states.push(State::SequenceEntry { initial_indent });
+                Ok(None)
+            }
+
+            State::ItemMappingKey { item_indent, indented } => {
+                if !self.at_block_key() {
+                    self.close_item_indent(indented);
+                    let span = self.current_span();
+                    return self.emit(Event::MappingEnd, span);
+                }
+
+                if matches!(self.peek(), Token::ExplicitKey) {
+                    self.advance();
+                    self.states.push(State::ItemExplicitValue { item_indent, indented });
+                    self.states.push(State::Node(Node::ListItemValue));
+                    return Ok(None);
+                }
+
+                let (key, quoted, span) = self.read_key()?;
+
+                // 最初の行の値が空で、次の行がキーと同じ列なら兄弟のキー
+                // （ただしキーと同じ列から始まるシーケンスはこのキーの値。
+                // indent_sequences を切ったエミッターは `- key:\n  - a` と書く）
+                match self.peek_indent_after_newlines() {
+                    Some(level) if !indented && level <= item_indent => {
+                        let null_span = self.current_span();
+                        self.skip_newlines();
+                        if matches!(self.peek_next(), Some(Token::ListItem)) {
+                            self.advance(); // consume indent
+                            self.states.push(State::ItemMappingEntryEnd { item_indent, indented: true });
+                            self.states.push(State::Node(Node::BlockSequence));
+                        } else {
+                            self.states.push(State::ItemMappingEntryEnd { item_indent, indented });
+                            self.states.push(State::Null(null_span));
+                        }
+                    }
+                    _ => {
+                        self.states.push(State::ItemMappingEntryEnd { item_indent, indented });
+                        self.states.push(State::Node(Node::MappingValue));
+                    }
+                }
+
+                self.key(key, quoted, span)
+            }
+
+            State::ItemMappingEntryEnd { item_indent, indented } => {
+                // 次の行を確認（続きの行はアイテムより深くインデントされている）
+                self.skip_newlines();
+                if !indented {
+                    if !matches!(self.peek(), Token::Indent(_)) {
+                        self.close_item_indent(false);
+                        let span = self.current_span();
+                        return self.emit(Event::MappingEnd, span);
+                    }
+                    self.advance();
+                }
+
+                self.states.push(State::ItemMappingKey { item_indent, indented: true });
+                Ok(None)
+            }
+
+            State::CompactSequenceEntry { indented } => {
+                if !matches!(self.peek(), Token::ListItem) {
+                    self.close_item_indent(indented);
+                    let span = self.current_span();
+                    return self.emit(Event::SequenceEnd, span);
+                }
+
+                self.advance(); // consume '-'
+                self.states.push(State::CompactSequenceEntryEnd { indented });
+                self.states.push(State::Node(Node::ListItemValue));
+                Ok(None)
+            }
+
+            State::CompactSequenceEntryEnd { indented } => {
+                // 続きのアイテムは外側の "- " の後の列に並ぶ
+                self.skip_newlines();
+                if !indented {
+                    if !matches!(self.peek(), Token::Indent(_)) {
+                        self.close_item_indent(false);
+                        let span = self.current_span();
+                        return self.emit(Event::SequenceEnd, span);
+                    }
+                    self.advance();
+                }
+
+                self.states.push(State::CompactSequenceEntry { indented: true });
+                Ok(None)
+            }
+
+            State::FlowSequenceEntry => {
+                self.skip_newlines();
+
+                if matches!(self.peek(), Token::FlowEnd(']')) {
+                    let span = self.current_span();
+                    self.advance();
+                    return self.emit(Event::SequenceEnd, span);
+                }
+
+                self.states.push(State::FlowSequenceEntryEnd);
+
+                // [a: b] のような単一ペアのマッピングも許可
+                if matches!(self.peek(), Token::Key(_) | Token::QuotedKey(_) | Token::ExplicitKey) {
+                    self.states.push(State::FlowSinglePairEnd);
+                    self.states.push(State::FlowPair);
+                    let span = self.current_span();
+                    return self.mapping_start(span);
+                }
+
+                self.states.push(State::Node(Node::FlowEntry));
+                Ok(None)
+            }
+
+            State::FlowSequenceEntryEnd => {
+                self.expect_flow_separator(']')?;
+                self.states.push(State::FlowSequenceEntry);
+                Ok(None)
+            }
+
+            State::FlowSinglePairEnd => {
+                let span = self.current_span();
+                self.emit(Event::MappingEnd, span)
+            }
+
+            State::FlowMappingEntry => {
+                self.skip_newlines();
+
+                if matches!(self.peek(), Token::FlowEnd('}')) {
+                    let span = self.current_span();
+                    self.advance();
+                    return self.emit(Event::MappingEnd, span);
+                }
+
+                self.states.push(State::FlowMappingEntryEnd);
+                self.states.push(State::FlowPair);
+                Ok(None)
+            }
+
+            State::FlowMappingEntryEnd => {
+                self.expect_flow_separator('}')?;
+                self.states.push(State::FlowMappingEntry);
+                Ok(None)
+            }
+
+            // フローマッピングのエントリ（`key: value`、値のない `key`、`? key : value`）
+            State::FlowPair => {
+                if matches!(self.peek(), Token::ExplicitKey) {
+                    self.advance();
+                    self.skip_newlines();
+                }
+                self.states.push(State::FlowPairValue);
+
+                let span = self.current_span();
+                let (key, quoted) = match self.peek() {
+                    Token::Key(k) | Token::Plain(k) => (k.clone(), false),
+                    Token::QuotedKey(k) | Token::Value(YamlValueRef::String(k)) => (k.clone(), true),
+                    // コレクションなどのキー（`[a, b]: c`）
+                    _ => {
+                        self.states.push(State::Node(Node::FlowEntry));
+                        return Ok(None);
+                    }
+                };
+                self.advance();
+                self.key(key, quoted, span)
+            }
+
+            State::FlowPairValue => {
+                self.skip_newlines();
+                if matches!(self.peek(), Token::Colon) {
+                    self.advance();
+                    self.skip_newlines();
+                }
+
+                if matches!(self.peek(), Token::FlowSeparator | Token::FlowEnd(_)) {
+                    let span = self.current_span();
+                    return self.scalar(YamlValueRef::Null, span);
+                }
+                self.states.push(State::Node(Node::FlowEntry));
+                Ok(None)
+            }
+
+            // `? key` の後の行頭の `: value`（なければ値は null）
+            State::ExplicitValue => {
+                self.skip_newlines();
+                let span = self.current_span();
+                if !matches!(self.peek(), Token::Colon) {
+                    return self.scalar(YamlValueRef::Null, span);
+                }
+
+                self.advance();
+                self.states.push(State::Node(Node::ListItemValue));
+                Ok(None)
+            }
+
+            // `- ? key` の後の `: value`。`:` はアイテムの続きの行にある
+            State::ItemExplicitValue { item_indent, mut indented } => {
+                self.skip_newlines();
+                if !indented && matches!(self.peek(), Token::Indent(_)) && matches!(self.peek_next(), Some(Token::Colon)) {
+                    self.advance();
+                    indented = true;
+                }
+                self.states.push(State::ItemMappingEntryEnd { item_indent, indented });
+
+                let span = self.current_span();
+                if !matches!(self.peek(), Token::Colon) {
+                    return self.scalar(YamlValueRef::Null, span);
+                }
+
+                self.advance();
+                self.states.push(State::Node(Node::ListItemValue));
+                Ok(None)
+            }
+        }
+    }
+
+    // ノードを1つ読み始める。アンカーは保留して最初のイベントに付ける
+    fn parse_node(&mut self, node: Node) -> Result<Option<SpannedEvent<'a>>> {
+        let entry_span = self.current_span();
+        if matches!(node, Node::Value | Node::FlowEntry | Node::MappingValue) {
+            self.skip_newlines();
+        }
+
+        match self.peek() {
+            Token::Anchor(name) => {
+                self.pending_anchor = Some(name.clone());
+                self.advance();
+                self.states.push(State::Node(node));
+                return Ok(None);
+            }
+            Token::Tag(tag) => {
+                let tag = tag.clone();
+                let span = self.current_span();
+                self.pending_tag = Some(self.expand_tag(&tag, span)?);
+                self.advance();
+                self.states.push(State::Node(node));
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        let span = self.current_span();
+        let has_properties = self.pending_tag.is_some() || self.pending_anchor.is_some();
+        match (node, self.peek()) {
+            (_, Token::Value(value)) if node != Node::BlockSequence => {
+                let value = value.clone();
+                self.advance();
+                self.scalar(value, span)
+            }
+            (_, Token::Plain(text)) if node != Node::BlockSequence => {
+                let text = text.clone();
+                self.advance();
+                self.plain_scalar(text, span)
+            }
+            (_, Token::FlowStart(open)) if node != Node::BlockSequence => {
+                let sequence = *open == '[';
+                self.advance();
+                if sequence {
+                    self.states.push(State::FlowSequenceEntry);
+                    self.sequence_start(span)
+                } else {
+                    self.states.push(State::FlowMappingEntry);
+                    self.mapping_start(span)
+                }
+            }
+            (_, Token::Alias(name)) if node != Node::BlockSequence => {
+                let name = name.clone();
+                self.advance();
+                if self.pending_anchor.is_some() {
+                    return Err(YamlError::ParseError {
+                        message: format!("An alias cannot have an anchor: *{}", name),
+                        span,
+                    });
+                }
+                if self.pending_tag.is_some() {
+                    return Err(YamlError::ParseError {
+                        message: format!("An alias cannot have a tag: *{}", name),
+                        span,
+                    });
+                }
+                self.emit(Event::Alias(name), span)
+            }
+            // タグやアンカーだけで内容のないノード（`[!tag, a]`）
+            (
+                Node::Value | Node::FlowEntry,
+                Token::FlowSeparator | Token::FlowEnd(_) | Token::Dedent(_) | Token::Eof | Token::DocumentStart | Token::DocumentEnd,
+            ) if has_properties => self.scalar(YamlValueRef::Null, span),
+            (Node::FlowEntry, _) => Err(self.unexpected_token("in flow collection")),
+
+            // ブロックシーケンス
+            (Node::Value | Node::MappingValue | Node::BlockSequence, Token::ListItem) => {
+                let initial_indent = self.get_current_indent();
+                self.states.push(State::SequenceEntry { initial_indent });
+                self.sequence_start(span)
+            }
+            (Node::BlockSequence, _) => Err(self.unexpected_token("where a sequence was expected")),
+
+            // ブロックマッピング
+            (Node::Value, Token::Key(_) | Token::QuotedKey(_) | Token::ExplicitKey) => {
+                let initial_indent = self.get_current_indent();
+                self.states.push(State::MappingKey { initial_indent });
+                self.mapping_start(span)
+            }
+            (Node::Value, _) => Err(self.unexpected_token("where a value was expected")),
+
+            // キーの後の次の行のネストした構造
+            (Node::MappingValue, Token::Indent(_)) => {
+                self.advance(); // consume indent
+                self.states.push(State::ConsumeDedent);
+                self.states.push(State::Node(Node::Value));
+                Ok(None)
+            }
+            (Node::MappingValue, _) => self.scalar(YamlValueRef::Null, entry_span),
+
+            // '-' の後の次の行のネストした構造
+            (Node::ListItemValue, Token::Newline) => {
+                self.advance();
+                if matches!(self.peek(), Token::Indent(_)) {
+                    self.advance();
+                    self.states.push(State::ConsumeDedent);
+                    self.states.push(State::Node(Node::Value));
+                    Ok(None)
+                } else {
+                    self.scalar(YamlValueRef::Null, span)
+                }
+            }
+            // リストアイテムと同じ行から始まるオブジェクト（`- key: value`）
+            (Node::ListItemValue, Token::Key(_) | Token::QuotedKey(_) | Token::ExplicitKey) => {
+                // "- " の後のキーの列
+                let item_indent = self.get_current_indent() + 2;
+                self.states.push(State::ItemMappingKey { item_indent, indented: false });
+                self.mapping_start(span)
+            }
+            // リストアイテムと同じ行から始まるシーケンス（`- - a`。
+            // エミッターはネストしたシーケンスをこの形で書く）
+            (Node::ListItemValue, Token::ListItem) => {
+                self.states.push(State::CompactSequenceEntry { indented: false });
+                self.sequence_start(span)
+            }
+            (Node::ListItemValue, _) => self.scalar(YamlValueRef::Null, span),
+        }
+    }
+
+    fn is_at_document_end(&mut self) -> bool {
+        matches!(self.peek(), Token::Eof | Token::DocumentStart | Token::DocumentEnd)
+    }
+
+    // エントリの後の ',' または閉じ括弧を確認（閉じ括弧は消費しない）
+    fn expect_flow_separator(&mut self, close: char) -> Result<()> {
+        self.skip_newlines();
+
+        match self.peek() {
+            Token::FlowSeparator => {
+                self.advance();
+                Ok(())
+            }
+            Token::FlowEnd(c) if *c == close => Ok(()),
+            _ => Err(self.unexpected_token(&format!("where ',' or '{}' was expected", close))),
+        }
+    }
+
+    // 続きの行のために開いたインデントを閉じる
+    fn close_item_indent(&mut self, indented: bool) {
+        self.skip_newlines();
+        if indented && matches!(self.peek(), Token::Dedent(_)) {
+            self.advance();
+        }
+    }
+
+    // 改行とコメントの後に続く Indent トークンのレベル
+    fn peek_indent_after_newlines(&mut self) -> Option<usize> {
+        let mut i = 0;
+        loop {
+            self.fill(i + 1);
+            match self.lookahead.get(i).map(|spanned| &spanned.token) {
+                Some(Token::Newline | Token::Comment(_)) => i += 1,
+                Some(Token::Indent(level)) => return Some(*level),
+                _ => return None,
+            }
+        }
+    }
+
+    // 現在のインデントレベル（advance で更新するため定数時間）
+    fn get_current_indent(&self) -> usize {
+        *self.indent_stack.last().unwrap()
+    }
+}
+
+// `!`、`!!` または `!word!` の形のハンドル
+fn is_tag_handle(handle: &str) -> bool {
+    handle == "!"
+        || (handle.len() >= 2
+            && handle.starts_with('!')
+            && handle.ends_with('!')
+            && handle[1..handle.len() - 1].bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-'))
+}
+
+// タグの接尾辞の %XX エスケープを戻す（不正なエスケープはそのまま残す）
+fn percent_decode(suffix: &str) -> Cow<'_, str> {
+    if !suffix.contains('%') {
+        return Cow::Borrowed(suffix);
+    }
+
+    let bytes = suffix.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| suffix.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<Event<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_spanned().map(|result| result.map(|spanned| spanned.event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn events(input: &str) -> Vec<Event<'_>> {
+        let tokens = Lexer::new(input).tokenize().unwrap();
+        Events::new(tokens).collect::<Result<_>>().unwrap()
+    }
+
+    fn string(s: &str) -> Event<'_> {
+        Event::Scalar { value: YamlValueRef::String(s.into()), anchor: None, tag: None }
+    }
+
+    fn int(i: i64) -> Event<'static> {
+        Event::Scalar { value: YamlValueRef::Integer(i), anchor: None, tag: None }
+    }
+
+    #[test]
+    fn test_block_events() {
+        assert_eq!(events("a: 1\nb:\n  - x\n  - &n 2\nc:"), vec![
+            Event::StreamStart,
+            Event::DocumentStart { explicit: false },
+            Event::MappingStart { anchor: None, tag: None },
+            string("a"),
+            int(1),
+            string("b"),
+            Event::SequenceStart { anchor: None, tag: None },
+            string("x"),
+            Event::Scalar { value: YamlValueRef::Integer(2), anchor: Some("n".to_string()), tag: None },
+            Event::SequenceEnd,
+            string("c"),
+            Event::Scalar { value: YamlValueRef::Null, anchor: None, tag: None },
+            Event::MappingEnd,
+            Event::DocumentEnd { explicit: false },
+            Event::StreamEnd,
+        ]);
+    }
+
+    #[test]
+    fn test_flow_and_alias_events() {
+        assert_eq!(events("- &m {k: v}\n- [*m, a: 1]")[2..], [
+            Event::SequenceStart { anchor: None, tag: None },
+            Event::MappingStart { anchor: Some("m".to_string()), tag: None },
+            string("k"),
+            string("v"),
+            Event::MappingEnd,
+            Event::SequenceStart { anchor: None, tag: None },
+            Event::Alias("m".to_string()),
+            Event::MappingStart { anchor: None, tag: None },
+            string("a"),
+            int(1),
+            Event::MappingEnd,
+            Event::SequenceEnd,
+            Event::SequenceEnd,
+            Event::DocumentEnd { explicit: false },
+            Event::StreamEnd,
+        ]);
+    }
+
+    #[test]
+    fn test_compact_sequence_events() {
+        assert_eq!(events("- - a\n  - b\n- k:\n  - c\n")[2..], [
+            Event::SequenceStart { anchor: None, tag: None },
+            Event::SequenceStart { anchor: None, tag: None },
+            string("a"),
+            string("b"),
+            Event::SequenceEnd,
+            Event::MappingStart { anchor: None, tag: None },
+            string("k"),
+            Event::SequenceStart { anchor: None, tag: None },
+            string("c"),
+            Event::SequenceEnd,
+            Event::MappingEnd,
+            Event::SequenceEnd,
+            Event::DocumentEnd { explicit: false },
+            Event::StreamEnd,
+        ]);
+    }
+
+    #[test]
+    fn test_complex_key_events() {
+        assert_eq!(events("? [a]\n: 1\n? b\n2: '3'\n")[2..], [
+            Event::MappingStart { anchor: None, tag: None },
+            Event::SequenceStart { anchor: None, tag: None },
+            string("a"),
+            Event::SequenceEnd,
+            int(1),
+            string("b"),
+            Event::Scalar { value: YamlValueRef::Null, anchor: None, tag: None },
+            int(2),
+            string("3"),
+            Event::MappingEnd,
+            Event::DocumentEnd { explicit: false },
+            Event::StreamEnd,
+        ]);
+    }
+
+    #[test]
+    fn test_document_events() {
+        assert_eq!(events("---\na\n...\n---\n"), vec![
+            Event::StreamStart,
+            Event::DocumentStart { explicit: true },
+            string("a"),
+            Event::DocumentEnd { explicit: true },
+            Event::DocumentStart { explicit: true },
+            Event::Scalar { value: YamlValueRef::Null, anchor: None, tag: None },
+            Event::DocumentEnd { explicit: false },
+            Event::StreamEnd,
+        ]);
+        assert_eq!(events(""), vec![Event::StreamStart, Event::StreamEnd]);
+    }
+
+    #[test]
+    fn test_tag_events() {
+        let tagged = |value, tag: &str| Event::Scalar { value, anchor: None, tag: Some(tag.to_string()) };
+        assert_eq!(events("- !!str 1\n- !!float 2\n- ! 3\n- !!int '4'\n- !!null\n- !Ref x\n- !Ref 5\n- !!binary aGk=")[3..11], [
+            string("1"),
+            Event::Scalar { value: YamlValueRef::Float(2.0), anchor: None, tag: None },
+            string("3"),
+            int(4),
+            Event::Scalar { value: YamlValueRef::Null, anchor: None, tag: None },
+            tagged(YamlValueRef::String("x".into()), "!Ref"),
+            tagged(YamlValueRef::Integer(5), "!Ref"),
+            tagged(YamlValueRef::String("aGk=".into()), "tag:yaml.org,2002:binary"),
+        ]);
+
+        // コレクションのタグ（!!map と !!seq は取り除く）
+        assert_eq!(events("a: !!map {b: !Seq [1]}\nc: !!seq\n  - !Foo {}")[4..], [
+            Event::MappingStart { anchor: None, tag: None },
+            string("b"),
+            Event::SequenceStart { anchor: None, tag: Some("!Seq".to_string()) },
+            int(1),
+            Event::SequenceEnd,
+            Event::MappingEnd,
+            string("c"),
+            Event::SequenceStart { anchor: None, tag: None },
+            Event::MappingStart { anchor: None, tag: Some("!Foo".to_string()) },
+            Event::MappingEnd,
+            Event::SequenceEnd,
+            Event::MappingEnd,
+            Event::DocumentEnd { explicit: false },
+            Event::StreamEnd,
+        ]);
+    }
+
+    #[test]
+    fn test_tag_directives() {
+        let yaml = "%YAML 1.2\n%TAG !e! tag:example.com,2000:app/\n%TAG ! tag:local,2024:\n--- !e!a%21\n- !e!foo x\n- !bar y\n- !<!verbatim> z\n...\n---\n- !bar y\n";
+        let tags: Vec<_> = events(yaml)
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Scalar { tag, .. } | Event::SequenceStart { tag, .. } => tag,
+                _ => None,
+            })
+            .collect();
+
+        // ディレクティブは直後のドキュメントにのみ適用される
+        assert_eq!(tags, [
+            "tag:example.com,2000:app/a!",
+            "tag:example.com,2000:app/foo",
+            "tag:local,2024:bar",
+            "!verbatim",
+            "!bar",
+        ]);
+    }
+
+    #[test]
+    fn test_tag_errors() {
+        let error = |input: &str| {
+            let tokens = Lexer::new(input).tokenize_with_spans().unwrap();
+            let error = Events::with_spans(tokens).find_map(|result| result.err()).unwrap();
+            (error.message(), error.span().map(|span| (span.line, span.column)))
+        };
+
+        assert_eq!(error("a: !!int abc"), ("Invalid value for !!int: \"abc\"".to_string(), Some((1, 10))));
+        assert_eq!(error("a: !!bool 1"), ("Invalid value for !!bool: \"1\"".to_string(), Some((1, 11))));
+        assert_eq!(error("a: !!str\n  b: 1"), ("Tag !!str cannot be applied to a mapping".to_string(), Some((2, 3))));
+        assert_eq!(error("a: !!seq x"), ("Tag !!seq cannot be applied to a scalar".to_string(), Some((1, 10))));
+        assert_eq!(error("a: !e!x 1"), ("Undefined tag handle: !e!".to_string(), Some((1, 4))));
+        assert_eq!(error("a: !e! 1"), ("Missing tag suffix after !e!".to_string(), Some((1, 4))));
+        assert_eq!(error("a: !t *b"), ("An alias cannot have a tag: *b".to_string(), Some((1, 7))));
+        assert_eq!(error("%TAG !e! a:\na: 1"), ("Expected '---' after directives".to_string(), Some((2, 1))));
+        assert_eq!(error("%TAG e a:\n---\n"), ("Invalid directive: %TAG e a:".to_string(), Some((1, 1))));
+        assert_eq!(
+            error("%TAG !e! a:\n%TAG !e! b:\n---\n"),
+            ("Duplicate %TAG directive for !e!".to_string(), Some((2, 1)))
+        );
+    }
+
+    #[test]
+    fn test_event_spans_and_errors() {
+        let tokens = Lexer::new("a:\n  b: [1, 2}").tokenize_with_spans().unwrap();
+        let mut events = Events::with_spans(tokens);
+        let spans: Vec<_> = std::iter::from_fn(|| events.next_spanned())
+            .take_while(|result| result.is_ok())
+            .map(|result| result.unwrap())
+            .filter(|spanned| matches!(spanned.event, Event::Scalar { .. }))
+            .map(|spanned| (spanned.span.line, spanned.span.column))
+            .collect();
+        assert_eq!(spans, vec![(1, 1), (2, 3), (2, 7), (2, 10)]);
+
+        // エラーの後は終了する
+        assert!(events.next().is_none());
+    }
+}
@@ -0,0 +1,226 @@
+//! Indexing into [`YamlValue`]
+//!
+//! ```rust
+//! use yaml_parser::{parse_yaml, YamlValue};
+//!
+//! let value = parse_yaml("server:\n  tls:\n    cert: /etc/cert.pem\n  ports: [80, 443]").unwrap();
+//! assert_eq!(value["server"]["tls"]["cert"], YamlValue::String("/etc/cert.pem".to_string()));
+//! assert_eq!(value["server"]["ports"][1], YamlValue::Integer(443));
+//! assert_eq!(value.pointer("/server/ports/0"), Some(&YamlValue::Integer(80)));
+//!
+//! // Missing entries index to `Null`
+//! assert_eq!(value["server"]["missing"][3], YamlValue::Null);
+//! ```
+
+use std::ops;
+
+use crate::value::YamlValue;
+
+// 存在しないエントリを添字で参照したときに返す値
+static NULL: YamlValue = YamlValue::Null;
+
+/// A type that can index into a [`YamlValue`]
+///
+/// Implemented for `usize` (sequence positions) and string types (mapping
+/// keys). A string matches a string key, or failing that a scalar key with
+/// the same text, so `value["200"]` finds the key of `200: OK`. Used by
+/// [`YamlValue::get`], [`YamlValue::get_mut`] and the `[]` operator.
+pub trait Index: private::Sealed {
+    #[doc(hidden)]
+    fn index_into<'v>(&self, value: &'v YamlValue) -> Option<&'v YamlValue>;
+
+    #[doc(hidden)]
+    fn index_into_mut<'v>(&self, value: &'v mut YamlValue) -> Option<&'v mut YamlValue>;
+}
+
+impl Index for usize {
+    fn index_into<'v>(&self, value: &'v YamlValue) -> Option<&'v YamlValue> {
+        match value {
+            YamlValue::Array(items) => items.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut YamlValue) -> Option<&'v mut YamlValue> {
+        match value {
+            YamlValue::Array(items) => items.get_mut(*self),
+            _ => None,
+        }
+    }
+}
+
+impl Index for str {
+    fn index_into<'v>(&self, value: &'v YamlValue) -> Option<&'v YamlValue> {
+        match value {
+            YamlValue::Object(map) => map.get_by_text(self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut YamlValue) -> Option<&'v mut YamlValue> {
+        match value {
+            YamlValue::Object(map) => map.get_by_text_mut(self),
+            _ => None,
+        }
+    }
+}
+
+impl Index for String {
+    fn index_into<'v>(&self, value: &'v YamlValue) -> Option<&'v YamlValue> {
+        self.as_str().index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut YamlValue) -> Option<&'v mut YamlValue> {
+        self.as_str().index_into_mut(value)
+    }
+}
+
+impl<T: Index + ?Sized> Index for &T {
+    fn index_into<'v>(&self, value: &'v YamlValue) -> Option<&'v YamlValue> {
+        (**self).index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut YamlValue) -> Option<&'v mut YamlValue> {
+        (**self).index_into_mut(value)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for usize {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl<T: Sealed + ?Sized> Sealed for &T {}
+}
+
+impl YamlValue {
+    /// Look up a mapping key or sequence position
+    ///
+    /// Returns `None` if the entry is missing or `self` is not a collection
+    /// of the matching kind.
+    pub fn get<I: Index>(&self, index: I) -> Option<&YamlValue> {
+        index.index_into(self)
+    }
+
+    /// Mutable version of [`YamlValue::get`]
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut YamlValue> {
+        index.index_into_mut(self)
+    }
+
+    /// Look up a nested value by JSON Pointer (RFC 6901)
+    ///
+    /// `""` refers to the whole value; otherwise each `/`-separated segment
+    /// selects a mapping key or a sequence position, with `~1` standing for
+    /// `/` and `~0` for `~` inside keys. Keys are matched as by the `[]`
+    /// operator, so `/responses/200` finds the integer key `200`.
+    pub fn pointer(&self, pointer: &str) -> Option<&YamlValue> {
+        let segments = pointer_segments(pointer)?;
+        segments.into_iter().try_fold(self, |value, segment| match value {
+            YamlValue::Array(items) => items.get(parse_position(&segment)?),
+            YamlValue::Object(map) => map.get_by_text(&segment),
+            _ => None,
+        })
+    }
+
+    /// Mutable version of [`YamlValue::pointer`]
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut YamlValue> {
+        let segments = pointer_segments(pointer)?;
+        segments.into_iter().try_fold(self, |value, segment| match value {
+            YamlValue::Array(items) => items.get_mut(parse_position(&segment)?),
+            YamlValue::Object(map) => map.get_by_text_mut(&segment),
+            _ => None,
+        })
+    }
+}
+
+// ポインターをエスケープ解除したセグメントに分割する（'/' で始まらなければ None）
+fn pointer_segments(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let rest = pointer.strip_prefix('/')?;
+    Some(
+        rest.split('/')
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+// シーケンスの位置（RFC 6901 では先頭の 0 や符号を許さない）
+fn parse_position(segment: &str) -> Option<usize> {
+    if segment.is_empty()
+        || !segment.bytes().all(|b| b.is_ascii_digit())
+        || (segment.len() > 1 && segment.starts_with('0'))
+    {
+        return None;
+    }
+    segment.parse().ok()
+}
+
+impl<I: Index> ops::Index<I> for YamlValue {
+    type Output = YamlValue;
+
+    /// Index into a mapping or sequence, returning `Null` for missing entries
+    fn index(&self, index: I) -> &YamlValue {
+        index.index_into(self).unwrap_or(&NULL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_yaml;
+
+    #[test]
+    fn test_index_and_get() {
+        let mut value = parse_yaml("server:\n  tls:\n    cert: a.pem\nports: [80, 443]").unwrap();
+        assert_eq!(value["server"]["tls"]["cert"], YamlValue::String("a.pem".to_string()));
+        assert_eq!(value["ports"][0], YamlValue::Integer(80));
+        assert_eq!(value["ports"][5], YamlValue::Null);
+        assert_eq!(value["ports"]["x"], YamlValue::Null);
+        assert_eq!(value[0], YamlValue::Null);
+
+        let key = "ports".to_string();
+        assert_eq!(value.get(&key).and_then(|ports| ports.get(1)), Some(&YamlValue::Integer(443)));
+        assert_eq!(value.get("missing"), None);
+
+        *value.get_mut("ports").and_then(|ports| ports.get_mut(1)).unwrap() = YamlValue::Integer(8443);
+        assert_eq!(value["ports"][1], YamlValue::Integer(8443));
+    }
+
+    #[test]
+    fn test_scalar_keys_by_text() {
+        let mut value = parse_yaml("responses:\n  200: OK\n  \"404\": missing\n  404: shadowed\n  true: yes\n  1.5: x").unwrap();
+        assert_eq!(value["responses"]["200"], YamlValue::from("OK"));
+        assert_eq!(value.pointer("/responses/200"), Some(&YamlValue::from("OK")));
+        assert_eq!(value.pointer("/responses/true"), Some(&YamlValue::from("yes")));
+        assert_eq!(value["responses"]["1.5"], YamlValue::from("x"));
+        // 文字列のキーが優先される
+        assert_eq!(value["responses"]["404"], YamlValue::from("missing"));
+        assert_eq!(value["responses"]["201"], YamlValue::Null);
+
+        *value.pointer_mut("/responses/200").unwrap() = YamlValue::from("Created");
+        assert_eq!(value.get("responses").and_then(|r| r.get("200")), Some(&YamlValue::from("Created")));
+        *value.get_mut("responses").and_then(|r| r.get_mut("true")).unwrap() = YamlValue::Null;
+        assert_eq!(value["responses"]["true"], YamlValue::Null);
+    }
+
+    #[test]
+    fn test_pointer() {
+        let mut value = parse_yaml("a/b: 1\nm~n: 2\nlist:\n  - x: [10, 20]\n\"\": 3").unwrap();
+        assert_eq!(value.pointer(""), Some(&value));
+        assert_eq!(value.pointer("/a~1b"), Some(&YamlValue::Integer(1)));
+        assert_eq!(value.pointer("/m~0n"), Some(&YamlValue::Integer(2)));
+        assert_eq!(value.pointer("/list/0/x/1"), Some(&YamlValue::Integer(20)));
+        assert_eq!(value.pointer("/"), Some(&YamlValue::Integer(3)));
+
+        assert_eq!(value.pointer("list"), None);
+        assert_eq!(value.pointer("/list/00"), None);
+        assert_eq!(value.pointer("/list/-"), None);
+        assert_eq!(value.pointer("/list/1"), None);
+        assert_eq!(value.pointer("/a~1b/c"), None);
+
+        *value.pointer_mut("/list/0/x/0").unwrap() = YamlValue::Null;
+        assert_eq!(value["list"][0]["x"][0], YamlValue::Null);
+    }
+}
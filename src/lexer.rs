@@ -1,47 +1,194 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
 use crate::error::{Result, YamlError};
-use crate::token::Token;
-use crate::value::YamlValue;
+use crate::schema::{is_tag_suffix, is_uri};
+use crate::token::{Span, SpannedToken, Token};
+use crate::value::YamlValueRef;
+
+// リーダーから読み込む場合、消費済みのバイト数がこれを超えたらバッファから捨てる
+const COMPACT_THRESHOLD: usize = 4096;
+
+// ブロックスカラーの末尾改行の扱い
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Chomping {
+    Strip, // -
+    Clip,  // 指定なし
+    Keep,  // +
+}
 
-pub struct Lexer {
-    input: Vec<char>,
-    position: usize,
+/// Tokenizer over a string or a buffered reader
+///
+/// Tokens can be collected with [`Lexer::tokenize_with_spans`] or pulled
+/// one at a time through the `Iterator` implementation, which is how the
+/// parser consumes them.
+pub struct Lexer<'a> {
+    input: Cow<'a, str>,       // 読み込み済みの入力（リーダーの場合は未消費の部分のみ）
+    position: usize,           // input 内のバイト位置
     line: usize,
     column: usize,
+    offset: usize,             // 入力先頭からのバイトオフセット
     indent_stack: Vec<usize>,  // インデントレベルのスタック
+    flow_depth: usize,         // フローコレクションのネスト深さ
+    reader: Option<Box<dyn BufRead + 'a>>, // 残りの入力（読み終えたら None）
+    pending: Vec<u8>,          // チャンク境界で分断されたUTF-8シーケンス
+    read_error: Option<YamlError>,
+    queue: VecDeque<SpannedToken<'a>>, // 生成済みで未返却のトークン
+    at_line_start: bool,
+    node_column: Option<usize>, // この行で最後に読んだキーまたは '-' の列（0 始まり）
+    compact_columns: Vec<usize>, // `- ` や `? ` の後から同じ行で始まったノードの列
+    after_indicator: bool,     // 直前のトークンが `- ` または `? ` だった
+    finished: bool,            // Eof を生成した、またはエラーで終了した
 }
 
-impl Lexer {
-    pub fn new(input: &str) -> Self {
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
         Self {
-            input: input.chars().collect(),
+            input: Cow::Borrowed(input),
             position: 0,
             line: 1,
             column: 1,
+            offset: 0,
             indent_stack: vec![0], // 初期インデントレベルは0
+            flow_depth: 0,
+            reader: None,
+            pending: Vec::new(),
+            read_error: None,
+            queue: VecDeque::new(),
+            at_line_start: true,
+            node_column: None,
+            compact_columns: Vec::new(),
+            after_indicator: false,
+            finished: false,
+        }
+    }
+
+    /// Create a lexer that reads its input from `reader` as tokens are requested
+    ///
+    /// Only the part of the input needed for the current token is kept in
+    /// memory. The input must be UTF-8; sequences split across reads are
+    /// reassembled.
+    pub fn from_reader<R: BufRead + 'a>(reader: R) -> Self {
+        Self {
+            reader: Some(Box::new(reader)),
+            ..Self::new("")
+        }
+    }
+
+    // index のバイトが読み込まれるまでリーダーから読む。読み込めたら true
+    #[inline]
+    fn fill(&mut self, index: usize) -> bool {
+        index < self.input.len() || self.read_until(index)
+    }
+
+    fn read_until(&mut self, index: usize) -> bool {
+        while index >= self.input.len() {
+            let Some(reader) = self.reader.as_mut() else {
+                return false;
+            };
+
+            let chunk = match reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.fail_read(e);
+                    return false;
+                }
+            };
+
+            if chunk.is_empty() {
+                self.reader = None;
+                if !self.pending.is_empty() {
+                    self.fail_read(Self::invalid_utf8());
+                }
+                return false;
+            }
+
+            let len = chunk.len();
+            self.pending.extend_from_slice(chunk);
+            reader.consume(len);
+
+            if let Err(e) = self.decode_pending() {
+                self.fail_read(e);
+                return false;
+            }
         }
+        true
     }
 
-    fn is_at_end(&self) -> bool {
-        self.position >= self.input.len()
+    // 完全なUTF-8シーケンスを文字に変換し、末尾の不完全な部分は次の読み込みまで残す
+    fn decode_pending(&mut self) -> io::Result<()> {
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(text) => {
+                self.input.to_mut().push_str(text);
+                self.pending.clear();
+                return Ok(());
+            }
+            Err(e) if e.error_len().is_some() => return Err(Self::invalid_utf8()),
+            Err(e) => e.valid_up_to(),
+        };
+
+        let text = std::str::from_utf8(&self.pending[..valid]).expect("validated above");
+        self.input.to_mut().push_str(text);
+        self.pending.drain(..valid);
+        Ok(())
     }
 
-    fn current_char(&self) -> char {
-        self.input.get(self.position).copied().unwrap_or('\0')
+    fn invalid_utf8() -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8")
     }
 
-    fn peek_char(&self) -> Option<char> {
-        self.input.get(self.position + 1).copied()
+    // 読み込みエラーを記録し、入力はそこで終わったものとして扱う
+    fn fail_read(&mut self, error: io::Error) {
+        self.reader = None;
+        self.read_error = Some(YamlError::Io(error));
     }
 
-    #[allow(dead_code)]
-    fn peek_n(&self, n: usize) -> Option<char> {
-        self.input.get(self.position + n).copied()
+    // 消費済みの文字を捨ててメモリ使用量を抑える（トークンの境界でのみ呼ぶ）
+    fn compact(&mut self) {
+        if self.reader.is_some() && self.position >= COMPACT_THRESHOLD {
+            self.input.to_mut().drain(..self.position);
+            self.position = 0;
+        }
+    }
+
+    fn byte_at(&mut self, index: usize) -> Option<u8> {
+        self.fill(index);
+        self.input.as_bytes().get(index).copied()
+    }
+
+    // index から始まる文字（文字の境界でなければ None）
+    fn char_at(&mut self, index: usize) -> Option<char> {
+        match self.byte_at(index)? {
+            b if b.is_ascii() => Some(b as char),
+            // 読み込みは完全なUTF-8シーケンス単位なので、文字全体がバッファにある
+            _ => self.input.get(index..)?.chars().next(),
+        }
+    }
+
+    fn is_at_end(&mut self) -> bool {
+        !self.fill(self.position)
+    }
+
+    fn current_char(&mut self) -> char {
+        self.char_at(self.position).unwrap_or('\0')
+    }
+
+    fn peek_char(&mut self) -> Option<char> {
+        let len = self.current_char().len_utf8();
+        self.char_at(self.position + len)
+    }
+
+    // n バイト先の文字（ASCII の並びの後でのみ使う）
+    fn peek_n(&mut self, n: usize) -> Option<char> {
+        self.char_at(self.position + n)
     }
 
     fn advance(&mut self) -> char {
-        if !self.is_at_end() {
-            let ch = self.current_char();
-            self.position += 1;
+        if let Some(ch) = self.char_at(self.position) {
+            self.position += ch.len_utf8();
+            self.offset += ch.len_utf8();
             
             if ch == '\n' {
                 self.line += 1;
@@ -55,12 +202,93 @@ impl Lexer {
         }
     }
 
+    // 現在位置
+    fn span(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+        }
+    }
+
+    fn unexpected_char(&mut self) -> YamlError {
+        YamlError::UnexpectedChar {
+            char: self.current_char(),
+            span: self.span(),
+        }
+    }
+
     fn skip_whitespace_except_newline(&mut self) {
-        while matches!(self.current_char(), ' ' | '\t' | '\r') {
-            self.advance();
+        while matches!(self.byte_at(self.position), Some(b' ' | b'\t' | b'\r')) {
+            self.advance_ascii();
+        }
+    }
+
+    // 現在の文字が改行以外の ASCII であるときの advance
+    fn advance_ascii(&mut self) {
+        self.position += 1;
+        self.offset += 1;
+        self.column += 1;
+    }
+
+    // 現在行を改行の手前まで読み込み、行末のバイト位置を返す
+    fn line_end(&mut self) -> usize {
+        self.line_end_from(self.position)
+    }
+
+    // start を含む行の行末のバイト位置
+    fn line_end_from(&mut self, start: usize) -> usize {
+        let mut from = start;
+        loop {
+            if let Some(i) = self.input.as_bytes()[from..].iter().position(|&b| b == b'\n') {
+                return from + i;
+            }
+            from = self.input.len();
+            if !self.fill(from) {
+                return from;
+            }
         }
     }
 
+    // 現在行で stop を満たす最初のバイトの位置（なければ行末）
+    fn scan_line(&mut self, stop: impl Fn(u8) -> bool) -> usize {
+        let end = self.line_end();
+        self.input.as_bytes()[self.position..end]
+            .iter()
+            .position(|&b| stop(b))
+            .map_or(end, |i| self.position + i)
+    }
+
+    // 現在位置からコメントの手前（コメントがなければ行末）までのバイト位置
+    fn comment_or_line_end(&mut self) -> usize {
+        let end = self.line_end();
+        find_comment(&self.input[self.position..end]).map_or(end, |i| self.position + i)
+    }
+
+    // 同じ行の end（文字の境界）まで進める
+    fn advance_to(&mut self, end: usize) {
+        let text = &self.input[self.position..end];
+        self.column += text.chars().count();
+        self.offset += text.len();
+        self.position = end;
+    }
+
+    // input[start..end]。文字列入力からは借用し、リーダーの入力はコピーする
+    fn slice(&self, start: usize, end: usize) -> Cow<'a, str> {
+        match self.input {
+            Cow::Borrowed(input) => Cow::Borrowed(&input[start..end]),
+            Cow::Owned(ref input) => Cow::Owned(input[start..end].to_string()),
+        }
+    }
+
+    // 前後の空白を除いた slice
+    fn slice_trimmed(&self, start: usize, end: usize) -> Cow<'a, str> {
+        let text = &self.input[start..end];
+        let trimmed_start = start + (text.len() - text.trim_start().len());
+        let trimmed_end = start + text.trim_end().len();
+        self.slice(trimmed_start, trimmed_end.max(trimmed_start))
+    }
+
     #[allow(dead_code)]
     fn skip_whitespace(&mut self) {
         while matches!(self.current_char(), ' ' | '\t' | '\r' | '\n') {
@@ -73,13 +301,13 @@ impl Lexer {
     }
 
     // インデント測定と DEDENTトークンの生成
-    fn handle_indentation(&mut self) -> Result<Vec<Token>> {
+    fn handle_indentation(&mut self) -> Result<Vec<Token<'a>>> {
         let mut tokens = Vec::new();
         let mut indent_level = 0;
         
         // 行の開始時のインデントを測定
-        while matches!(self.current_char(), ' ' | '\t') {
-            self.advance();
+        while matches!(self.byte_at(self.position), Some(b' ' | b'\t')) {
+            self.advance_ascii();
             indent_level += 1;
         }
         
@@ -88,9 +316,21 @@ impl Lexer {
             return Ok(tokens);
         }
         
+        // `- key:` のキーの列は、スタックになくても戻れるレベル。
+        // `- ` より深くキーより浅い列は、どのノードにも属さない
+        let compact = self.compact_columns.contains(&indent_level);
+        let inside_item = self.compact_columns.iter().any(|&column| column > indent_level);
+        self.compact_columns.retain(|&column| column < indent_level);
+
         let current_indent = *self.indent_stack.last().unwrap();
         
         if indent_level > current_indent {
+            if inside_item && !compact {
+                return Err(YamlError::IndentationError {
+                    message: format!("Invalid indentation level {}", indent_level),
+                    span: self.span(),
+                });
+            }
             // インデント増加
             self.indent_stack.push(indent_level);
             tokens.push(Token::Indent(indent_level));
@@ -104,138 +344,696 @@ impl Lexer {
                 tokens.push(Token::Dedent(stack_indent));
             }
             
-            // 不正なインデントレベルの検出
+            // `- key:\n      x: 1\n  next:` のように `- ` の後のノードの列へ
+            // 戻る場合はそのレベルを新しく開く。それ以外の不明なレベルはエラー
             if self.indent_stack.last() != Some(&indent_level) {
-                return Err(YamlError::IndentationError(
-                    format!("Invalid indentation level {} at line {}", indent_level, self.line)
-                ));
+                if !compact {
+                    return Err(YamlError::IndentationError {
+                        message: format!("Invalid indentation level {}", indent_level),
+                        span: self.span(),
+                    });
+                }
+                self.indent_stack.push(indent_level);
+                tokens.push(Token::Indent(indent_level));
             }
         }
         
         Ok(tokens)
     }
 
-    fn read_comment(&mut self) -> Token {
-        self.advance(); // '#'をスキップ
-        let mut comment = String::new();
+    // エラーの後はトークンを返さない
+    fn fail(&mut self) {
+        self.finished = true;
+        self.queue.clear();
+    }
 
-        while !matches!(self.current_char(), '\n' | '\0') {
-            comment.push(self.advance());
+    // 開いているインデントレベルをすべて閉じる
+    fn close_indentation(&mut self) -> Vec<Token<'a>> {
+        let mut tokens = Vec::new();
+        while self.indent_stack.len() > 1 {
+            let indent_level = self.indent_stack.pop().unwrap();
+            tokens.push(Token::Dedent(indent_level));
+        }
+        tokens
+    }
+
+    // 行頭の `---` または `...` を読み取る
+    fn read_document_marker(&mut self) -> Option<Token<'a>> {
+        let marker = match (self.current_char(), self.peek_char(), self.peek_n(2)) {
+            ('-', Some('-'), Some('-')) => Token::DocumentStart,
+            ('.', Some('.'), Some('.')) => Token::DocumentEnd,
+            _ => return None,
+        };
+
+        if !matches!(self.peek_n(3), None | Some(' ' | '\t' | '\r' | '\n')) {
+            return None;
         }
 
-        Token::Comment(comment.trim().to_string())
+        for _ in 0..3 {
+            self.advance();
+        }
+        Some(marker)
+    }
+
+    fn read_comment(&mut self) -> Token<'a> {
+        self.advance(); // '#'をスキップ
+        let start_pos = self.position;
+
+        let end = self.line_end();
+        self.advance_to(end);
+
+        Token::Comment(self.input[start_pos..self.position].trim().to_string())
     }
 
-    // 値を読み取って適切な型に変換
-    fn read_value(&mut self) -> Result<YamlValue> {
+    // 値を読み取る。プレーンスカラーの型はここでは決めない
+    fn read_value(&mut self) -> Result<Token<'a>> {
         self.skip_whitespace_except_newline();
         
         if self.is_at_end() || matches!(self.current_char(), '\n' | '#') {
-            return Ok(YamlValue::Null);
+            return Ok(Token::Value(YamlValueRef::Null));
         }
         
         let start_pos = self.position;
         
         // 引用符付き文字列の処理
         if matches!(self.current_char(), '"' | '\'') {
-            return self.read_quoted_string();
+            return Ok(Token::Value(YamlValueRef::String(self.read_quoted_string()?)));
+        }
+
+        // ブロックスカラー（| または >）
+        if matches!(self.current_char(), '|' | '>')
+            && matches!(self.peek_char(), None | Some(' ' | '\t' | '\r' | '\n' | '+' | '-' | '1'..='9'))
+        {
+            return Ok(Token::Value(self.read_block_scalar()?));
         }
         
         // 通常の値を読み取り
-        while !matches!(self.current_char(), '\n' | '\0' | '#') {
+        let end = self.comment_or_line_end();
+        self.advance_to(end);
+        let text = self.slice_trimmed(start_pos, self.position);
+
+        // コメントで終わる行の後には続かない
+        if self.current_char() == '#' {
+            return Ok(Token::Plain(text));
+        }
+
+        // 続きの行は親のノード（同じ行のキーまたは '-'）より深くインデントされている
+        // 必要がある。行頭から始まるスカラー（`key:` の次の行など）の親は一つ外側のレベル
+        let parent_indent = self
+            .node_column
+            .or_else(|| self.indent_stack.iter().rev().nth(1).copied());
+        let min_indent = parent_indent.map_or(0, |indent| indent + 1);
+
+        match self.read_plain_continuation(min_indent) {
+            Some(rest) => Ok(Token::Plain(Cow::Owned(text.into_owned() + &rest))),
+            None => Ok(Token::Plain(text)),
+        }
+    }
+
+    // プレーンスカラーの続きの行を折り畳んで返す（改行は空白に、空行は改行になる）。
+    // 続きの行がなければ何も消費しない
+    fn read_plain_continuation(&mut self, min_indent: usize) -> Option<String> {
+        let mut folded = String::new();
+        let mut empty_lines = 0;
+        let mut consumed = None; // 最後の続きの行の終わり
+        let mut pos = self.position;
+
+        while self.byte_at(pos) == Some(b'\n') {
+            let line_start = pos + 1;
+            let line_end = self.line_end_from(line_start);
+            pos = line_end;
+
+            let line = &self.input[line_start..line_end];
+            let content = line.trim_start_matches([' ', '\t']);
+            let indent = line.len() - content.len();
+            if content.trim_end().is_empty() {
+                empty_lines += 1;
+                continue;
+            }
+
+            // インデントの浅い行、コメント行、ドキュメント境界、キーの行で終わる
+            let comment = find_comment(content);
+            let text = content[..comment.unwrap_or(content.len())].trim_end();
+            if indent < min_indent || text.is_empty() || is_document_marker(line) || has_key_indicator(text) {
+                break;
+            }
+
+            if empty_lines == 0 {
+                folded.push(' ');
+            } else {
+                folded.push_str(&"\n".repeat(empty_lines));
+            }
+            folded.push_str(text);
+            empty_lines = 0;
+
+            // コメントは次のトークンとして読む
+            consumed = Some(line_start + indent + comment.unwrap_or(content.len()));
+            if comment.is_some() {
+                break;
+            }
+        }
+
+        let end = consumed?;
+        while self.position < end {
             self.advance();
         }
-        
-        let value_str = self.input[start_pos..self.position]
-            .iter()
-            .collect::<String>()
-            .trim()
-            .to_string();
-            
-        self.parse_scalar_value(&value_str)
+        Some(folded)
     }
 
-    fn read_quoted_string(&mut self) -> Result<YamlValue> {
+    // 引用符付きスカラーを読み取る。エスケープや改行を含まなければ入力から借用する
+    fn read_quoted_string(&mut self) -> Result<Cow<'a, str>> {
+        let start = self.span();
         let quote_char = self.advance(); // " or '
-        let mut value = String::new();
-        
-        while !self.is_at_end() && self.current_char() != quote_char {
-            let ch = self.advance();
-            if ch == '\\' && quote_char == '"' {
-                // エスケープシーケンスの処理（ダブルクォートのみ）
-                match self.current_char() {
-                    'n' => { self.advance(); value.push('\n'); }
-                    't' => { self.advance(); value.push('\t'); }
-                    'r' => { self.advance(); value.push('\r'); }
-                    '\\' => { self.advance(); value.push('\\'); }
-                    '"' => { self.advance(); value.push('"'); }
-                    _ => {
+        let content_start = self.position;
+        let mut owned: Option<String> = None; // 最初の書き換え以降に作る
+        let mut trailing_space: Option<usize> = None; // 行末の空白の開始位置（owned 内）
+
+        loop {
+            if self.is_at_end() {
+                return Err(YamlError::ParseError {
+                    message: "Unterminated quoted string".to_string(),
+                    span: start,
+                });
+            }
+
+            let ch_pos = self.position;
+            let ch = self.current_char();
+
+            if ch == quote_char {
+                // シングルクォート内の '' は ' 1文字
+                if quote_char == '\'' && self.peek_char() == Some('\'') {
+                    let value = owned.get_or_insert_with(|| self.input[content_start..ch_pos].to_string());
+                    value.push('\'');
+                    trailing_space = None;
+                    self.advance();
+                    self.advance();
+                    continue;
+                }
+                break;
+            }
+
+            match ch {
+                '\\' if quote_char == '"' => {
+                    let value = owned.get_or_insert_with(|| self.input[content_start..ch_pos].to_string());
+                    self.read_escape(value)?;
+                    trailing_space = None;
+                }
+                '\n' | '\r' if ch == '\n' || self.peek_char() == Some('\n') => {
+                    // 改行の折り畳み: 前後の空白を除き、単独の改行は空白、空行は改行になる
+                    let value = owned.get_or_insert_with(|| self.input[content_start..ch_pos].to_string());
+                    if let Some(len) = trailing_space.take() {
+                        value.truncate(len);
+                    }
+                    self.skip_line_break();
+                    self.skip_whitespace_except_newline();
+                    let mut empty_lines = 0;
+                    while matches!(self.current_char(), '\n') {
+                        self.advance();
+                        self.skip_whitespace_except_newline();
+                        empty_lines += 1;
+                    }
+                    if empty_lines == 0 {
+                        value.push(' ');
+                    } else {
+                        value.push_str(&"\n".repeat(empty_lines));
+                    }
+                }
+                _ => {
+                    self.advance();
+                    if let Some(value) = owned.as_mut() {
+                        if matches!(ch, ' ' | '\t') {
+                            trailing_space.get_or_insert(value.len());
+                        } else {
+                            trailing_space = None;
+                        }
                         value.push(ch);
-                        value.push(self.advance());
+                    } else if matches!(ch, ' ' | '\t') {
+                        trailing_space.get_or_insert(ch_pos - content_start);
+                    } else {
+                        trailing_space = None;
                     }
                 }
-            } else {
+            }
+        }
+
+        let value = match owned {
+            Some(value) => Cow::Owned(value),
+            None => self.slice(content_start, self.position),
+        };
+        self.advance(); // 終了クォートをスキップ
+        Ok(value)
+    }
+
+    // '\n' または "\r\n" を1つ読み飛ばす
+    fn skip_line_break(&mut self) {
+        if self.current_char() == '\r' {
+            self.advance();
+        }
+        self.advance();
+    }
+
+    // ダブルクォート内のエスケープシーケンス（'\' から）を読み、value に追加する
+    fn read_escape(&mut self, value: &mut String) -> Result<()> {
+        let start = self.span();
+        self.advance(); // '\'
+
+        let escaped = match self.current_char() {
+            '0' => '\0',
+            'a' => '\u{07}',
+            'b' => '\u{08}',
+            't' | '\t' => '\t',
+            'n' => '\n',
+            'v' => '\u{0B}',
+            'f' => '\u{0C}',
+            'r' => '\r',
+            'e' => '\u{1B}',
+            ' ' => ' ',
+            '"' => '"',
+            '/' => '/',
+            '\\' => '\\',
+            'N' => '\u{85}',
+            '_' => '\u{A0}',
+            'L' => '\u{2028}',
+            'P' => '\u{2029}',
+            'x' => return self.read_hex_escape(2, start, value),
+            'u' => return self.read_hex_escape(4, start, value),
+            'U' => return self.read_hex_escape(8, start, value),
+            '\n' | '\r' => {
+                // エスケープされた改行: 改行と次の行頭の空白を除く（空行は改行として残る）
+                self.skip_line_break();
+                self.skip_whitespace_except_newline();
+                while self.current_char() == '\n' {
+                    self.advance();
+                    self.skip_whitespace_except_newline();
+                    value.push('\n');
+                }
+                return Ok(());
+            }
+            '\0' if self.is_at_end() => {
+                return Err(YamlError::ParseError {
+                    message: "Unterminated escape sequence".to_string(),
+                    span: start,
+                });
+            }
+            other => {
+                return Err(YamlError::ParseError {
+                    message: format!("Invalid escape sequence: \\{}", other),
+                    span: start,
+                });
+            }
+        };
+
+        self.advance();
+        value.push(escaped);
+        Ok(())
+    }
+
+    // \xXX、\uXXXX、\UXXXXXXXX（digits 桁の16進数）
+    fn read_hex_escape(&mut self, digits: usize, start: Span, value: &mut String) -> Result<()> {
+        let indicator = self.advance();
+        let mut code = 0u32;
+
+        for _ in 0..digits {
+            match self.current_char().to_digit(16) {
+                Some(digit) => {
+                    code = code * 16 + digit;
+                    self.advance();
+                }
+                None => {
+                    return Err(YamlError::ParseError {
+                        message: format!("Invalid escape sequence: \\{} needs {} hex digits", indicator, digits),
+                        span: start,
+                    });
+                }
+            }
+        }
+
+        match char::from_u32(code) {
+            Some(ch) => {
                 value.push(ch);
+                Ok(())
             }
+            None => Err(YamlError::ParseError {
+                message: format!("Invalid Unicode code point in escape sequence: {:X}", code),
+                span: start,
+            }),
         }
-        
-        if self.current_char() == quote_char {
-            self.advance(); // 終了クォートをスキップ
+    }
+
+    // リテラル（|）または折り畳み（>）ブロックスカラーを読み取る
+    fn read_block_scalar(&mut self) -> Result<YamlValueRef<'a>> {
+        let literal = self.advance() == '|';
+        let mut chomping = Chomping::Clip;
+        let mut explicit_indent = None;
+
+        // ヘッダー: チョンピング指示子とインデント指示子（順不同）
+        loop {
+            match self.current_char() {
+                '-' if chomping == Chomping::Clip => chomping = Chomping::Strip,
+                '+' if chomping == Chomping::Clip => chomping = Chomping::Keep,
+                c @ '1'..='9' if explicit_indent.is_none() => {
+                    explicit_indent = c.to_digit(10).map(|d| d as usize);
+                }
+                _ => break,
+            }
+            self.advance();
+        }
+
+        self.skip_whitespace_except_newline();
+        if self.current_char() == '#' {
+            self.read_comment();
+        }
+        if !matches!(self.current_char(), '\n' | '\0') {
+            return Err(self.unexpected_char());
+        }
+
+        let parent_indent = *self.indent_stack.last().unwrap();
+        let mut content_indent = explicit_indent.map(|n| parent_indent + n);
+
+        // 内容行を先読みし、ブロックの終端（インデントの浅い行）を探す
+        let mut lines: Vec<String> = Vec::new();
+        let mut terminated: Vec<bool> = Vec::new();
+        let mut end = self.position;
+
+        while self.fill(end + 1) {
+            let line_start = end + 1;
+            let mut p = line_start;
+            while self.byte_at(p) == Some(b' ') {
+                p += 1;
+            }
+            let spaces = p - line_start;
+
+            let mut line_end = p;
+            while matches!(self.byte_at(line_end), Some(b) if b != b'\n') {
+                line_end += 1;
+            }
+
+            let is_blank = self.input[p..line_end].bytes().all(|b| b == b'\r');
+            if !is_blank {
+                let indent = match content_indent {
+                    Some(indent) => indent,
+                    None if spaces > parent_indent => {
+                        content_indent = Some(spaces);
+                        spaces
+                    }
+                    None => break,
+                };
+                if spaces < indent {
+                    break;
+                }
+            }
+
+            let text = if is_blank {
+                String::new()
+            } else {
+                let indent = content_indent.unwrap_or(0);
+                self.input[line_start + indent..line_end]
+                    .trim_end_matches('\r')
+                    .to_string()
+            };
+            lines.push(text);
+            terminated.push(line_end < self.input.len()); // byte_at で読み込み済み
+            end = line_end;
+        }
+
+        // 内容を消費する（行・列の追跡のため advance を使う）
+        while self.position < end {
+            self.advance();
+        }
+
+        // 末尾の空行を分離
+        let content_len = lines
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .map_or(0, |i| i + 1);
+        let trailing_breaks = terminated[content_len..].iter().filter(|t| **t).count();
+        let content = &lines[..content_len];
+
+        let mut value = if literal {
+            content.join("\n")
         } else {
-            return Err(YamlError::UnexpectedEof);
+            Self::fold_lines(content)
+        };
+
+        let last_terminated = content_len > 0 && terminated[content_len - 1];
+        match chomping {
+            Chomping::Strip => {}
+            Chomping::Clip => {
+                if last_terminated {
+                    value.push('\n');
+                }
+            }
+            Chomping::Keep => {
+                if last_terminated {
+                    value.push('\n');
+                }
+                value.push_str(&"\n".repeat(trailing_breaks));
+            }
         }
-        
-        Ok(YamlValue::String(value))
+
+        Ok(YamlValueRef::String(Cow::Owned(value)))
     }
 
-    fn parse_scalar_value(&self, value: &str) -> Result<YamlValue> {
-        match value {
-            "true" | "True" | "TRUE" => Ok(YamlValue::Boolean(true)),
-            "false" | "False" | "FALSE" => Ok(YamlValue::Boolean(false)),
-            "null" | "Null" | "NULL" | "~" | "" => Ok(YamlValue::Null),
-            _ => {
-                // 数値の解析を試行
-                if let Ok(int_val) = value.parse::<i64>() {
-                    Ok(YamlValue::Integer(int_val))
-                } else if let Ok(float_val) = value.parse::<f64>() {
-                    Ok(YamlValue::Float(float_val))
-                } else {
-                    Ok(YamlValue::String(value.to_string()))
+    // 折り畳みスカラーの行結合: 通常の改行は空白に、空行は改行になる。
+    // より深くインデントされた行の前後では改行を保持する。
+    fn fold_lines(lines: &[String]) -> String {
+        let mut value = String::new();
+        let mut previous: Option<&str> = None;
+        let mut empty_lines = 0;
+
+        for line in lines {
+            if line.is_empty() {
+                empty_lines += 1;
+                continue;
+            }
+
+            let more_indented = |s: &str| s.starts_with([' ', '\t']);
+            match previous {
+                Some(prev) if more_indented(prev) || more_indented(line) => {
+                    value.push_str(&"\n".repeat(empty_lines + 1));
                 }
+                Some(_) if empty_lines == 0 => value.push(' '),
+                _ => value.push_str(&"\n".repeat(empty_lines)),
             }
+
+            value.push_str(line);
+            previous = Some(line);
+            empty_lines = 0;
         }
+
+        value
     }
 
-    fn read_key(&mut self) -> Result<String> {
+    fn read_key(&mut self) -> Result<Cow<'a, str>> {
+        let start = self.span();
         let start_pos = self.position;
         
         // キーの読み取り（コロンまで）
-        while !matches!(self.current_char(), ':' | '\n' | '\0') {
-            self.advance();
-        }
+        let end = self.scan_line(|b| b == b':');
+        self.advance_to(end);
         
         if start_pos == self.position {
-            return Err(YamlError::ParseError("Empty key".to_string()));
+            return Err(YamlError::ParseError {
+                message: "Empty key".to_string(),
+                span: start,
+            });
         }
         
-        let key = self.input[start_pos..self.position]
-            .iter()
-            .collect::<String>()
-            .trim()
-            .to_string();
-            
+        let key = self.slice_trimmed(start_pos, self.position);
+
         if key.is_empty() {
-            return Err(YamlError::ParseError("Empty key after trimming".to_string()));
+            return Err(YamlError::ParseError {
+                message: "Empty key after trimming".to_string(),
+                span: start,
+            });
         }
         
         Ok(key)
     }
 
-    fn next_token(&mut self) -> Result<Option<Token>> {
+    // アンカー名またはエイリアス名（& / * の後）を読み取る
+    fn read_anchor_name(&mut self) -> Result<String> {
+        let start = self.span();
+        let indicator = self.advance();
+        let start_pos = self.position;
+
+        while !matches!(self.current_char(), ' ' | '\t' | '\r' | '\n' | '\0' | ',' | '[' | ']' | '{' | '}') {
+            self.advance();
+        }
+
+        if start_pos == self.position {
+            return Err(YamlError::ParseError {
+                message: format!("Empty name after '{}'", indicator),
+                span: start,
+            });
+        }
+
+        Ok(self.input[start_pos..self.position].to_string())
+    }
+
+    // タグ（`!` から）を読み取る。ハンドルの展開はイベントパーサーが行う
+    fn read_tag(&mut self) -> Result<String> {
+        let start = self.span();
+        let start_pos = self.position;
+        self.advance(); // '!'
+
+        if self.current_char() == '<' {
+            // 逐語的タグ（!<tag:example.com,2000:app>）
+            let end = self.scan_line(|b| b == b'>');
+            if self.byte_at(end) != Some(b'>') || end == self.position + 1 {
+                return Err(YamlError::ParseError {
+                    message: "Unterminated verbatim tag".to_string(),
+                    span: start,
+                });
+            }
+            if !is_uri(&self.input[self.position + 1..end]) {
+                return Err(YamlError::ParseError {
+                    message: "Invalid character in verbatim tag".to_string(),
+                    span: start,
+                });
+            }
+            self.advance_to(end + 1);
+        } else {
+            while !matches!(self.current_char(), ' ' | '\t' | '\r' | '\n' | '\0' | ',' | '[' | ']' | '{' | '}') {
+                self.advance();
+            }
+
+            // `!e!suffix` のハンドル名は英数字と `-`、接尾辞は URI の文字だけ。
+            // 接尾辞のない `!` や `!e!` はイベントパーサーが扱う
+            let tag = &self.input[start_pos + 1..self.position];
+            let (name, suffix) = match tag.find('!') {
+                Some(i) => (&tag[..i], &tag[i + 1..]),
+                None => ("", tag),
+            };
+            if !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                || !(suffix.is_empty() || is_tag_suffix(suffix))
+            {
+                return Err(YamlError::ParseError {
+                    message: "Invalid character in tag".to_string(),
+                    span: start,
+                });
+            }
+        }
+
+        Ok(self.input[start_pos..self.position].to_string())
+    }
+
+    // 行頭の `%` から始まるディレクティブを読み取る（コメントは除く）
+    fn read_directive(&mut self) -> Token<'a> {
+        self.advance(); // '%'
+        let start_pos = self.position;
+
+        let end = self.line_end();
+        let line = &self.input.as_bytes()[start_pos..end];
+        let end = (1..line.len())
+            .find(|&i| line[i] == b'#' && matches!(line[i - 1], b' ' | b'\t'))
+            .map_or(end, |i| start_pos + i);
+        self.advance_to(end);
+
+        Token::Directive(self.input[start_pos..end].trim().to_string())
+    }
+
+    // フローコレクション内のプレーンスカラーまたはキーを読み取る
+    fn read_flow_scalar(&mut self) -> Result<Token<'a>> {
+        let start = self.span();
+        let start_pos = self.position;
+        let mut is_key = false;
+
+        loop {
+            match self.current_char() {
+                '\0' | '\n' | ',' | '[' | ']' | '{' | '}' => break,
+                ':' if matches!(
+                    self.peek_char(),
+                    None | Some(' ' | '\t' | '\r' | '\n' | ',' | '[' | ']' | '{' | '}')
+                ) => {
+                    is_key = true;
+                    break;
+                }
+                '#' if self.position > start_pos
+                    && matches!(self.input.as_bytes()[self.position - 1], b' ' | b'\t') =>
+                {
+                    break;
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+
+        let text = self.slice_trimmed(start_pos, self.position);
+
+        if is_key {
+            if text.is_empty() {
+                return Err(YamlError::ParseError {
+                    message: "Empty key".to_string(),
+                    span: start,
+                });
+            }
+            Ok(Token::Key(text))
+        } else {
+            Ok(Token::Plain(text))
+        }
+    }
+
+    // フローコレクション内のトークン（改行とインデントは意味を持たない）
+    fn next_flow_token(&mut self) -> Result<Option<Token<'a>>> {
+        match self.current_char() {
+            '\n' => {
+                self.advance();
+                Ok(None)
+            }
+            '#' => Ok(Some(self.read_comment())),
+            '[' | '{' => {
+                let ch = self.advance();
+                self.flow_depth += 1;
+                Ok(Some(Token::FlowStart(ch)))
+            }
+            ']' | '}' => {
+                let ch = self.advance();
+                self.flow_depth -= 1;
+                Ok(Some(Token::FlowEnd(ch)))
+            }
+            ',' => {
+                self.advance();
+                Ok(Some(Token::FlowSeparator))
+            }
+            '&' => Ok(Some(Token::Anchor(self.read_anchor_name()?))),
+            '*' => Ok(Some(Token::Alias(self.read_anchor_name()?))),
+            '!' => Ok(Some(Token::Tag(self.read_tag()?))),
+            // 予約済みインジケーターでプレーンスカラーは始められない
+            '@' | '`' => Err(self.unexpected_char()),
+            ':' => {
+                self.advance();
+                Ok(Some(Token::Colon))
+            }
+            '?' if matches!(self.peek_char(), None | Some(' ' | '\t' | '\r' | '\n')) => {
+                self.advance();
+                Ok(Some(Token::ExplicitKey))
+            }
+            '"' | '\'' => {
+                let value = self.read_quoted_string()?;
+                self.skip_whitespace_except_newline();
+
+                // 引用符付きキー（JSON形式の "key":value も許可）
+                if self.current_char() == ':' {
+                    Ok(Some(Token::QuotedKey(value)))
+                } else {
+                    Ok(Some(Token::Value(YamlValueRef::String(value))))
+                }
+            }
+            _ => Ok(Some(self.read_flow_scalar()?)),
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token<'a>>> {
         if self.is_at_end() {
             return Ok(Some(Token::Eof));
         }
 
+        if self.flow_depth > 0 {
+            return self.next_flow_token();
+        }
+
         match self.current_char() {
             '\n' => {
                 self.advance();
@@ -243,110 +1041,203 @@ impl Lexer {
             }
             '#' => Ok(Some(self.read_comment())),
             ':' => {
+                // 行頭の `:` は明示的なキー（`? key`）の値
+                if self.node_column.is_none() {
+                    self.node_column = Some(self.column - 1);
+                }
                 self.advance();
                 Ok(Some(Token::Colon))
             }
-            '-' => {
-                if self.peek_char() == Some(' ') || self.peek_char() == Some('\n') {
-                    self.advance(); // '-'
-                    if self.current_char() == ' ' {
-                        self.advance(); // ' '
-                    }
-                    Ok(Some(Token::ListItem))
+            '[' | '{' => {
+                let ch = self.advance();
+                self.flow_depth += 1;
+                Ok(Some(Token::FlowStart(ch)))
+            }
+            '&' => Ok(Some(Token::Anchor(self.read_anchor_name()?))),
+            '*' => Ok(Some(Token::Alias(self.read_anchor_name()?))),
+            '!' => Ok(Some(Token::Tag(self.read_tag()?))),
+            // 予約済みインジケーターと対応のない閉じ括弧
+            '@' | '`' | ']' | '}' => Err(self.unexpected_char()),
+            '?' if matches!(self.peek_char(), None | Some(' ' | '\t' | '\r' | '\n')) => {
+                self.node_column = Some(self.column - 1);
+                self.advance(); // '?'
+                if self.current_char() == ' ' {
+                    self.advance(); // ' '
+                }
+                Ok(Some(Token::ExplicitKey))
+            }
+            // ダッシュで始まるキーや値（`-1: neg`）は下で読む
+            '-' if self.peek_char() == Some(' ') || self.peek_char() == Some('\n') => {
+                self.node_column = Some(self.column - 1);
+                self.advance(); // '-'
+                if self.current_char() == ' ' {
+                    self.advance(); // ' '
+                }
+                Ok(Some(Token::ListItem))
+            }
+            '"' | '\'' => {
+                let column = self.column - 1;
+                let value = self.read_quoted_string()?;
+                self.skip_whitespace_except_newline();
+
+                // 引用符付きキー
+                if self.current_char() == ':'
+                    && matches!(self.peek_char(), None | Some(' ' | '\t' | '\r' | '\n'))
+                {
+                    self.node_column = Some(column);
+                    Ok(Some(Token::QuotedKey(value)))
                 } else {
-                    // ダッシュで始まる値として扱う
-                    let value = self.read_value()?;
-                    Ok(Some(Token::Value(value)))
+                    Ok(Some(Token::Value(YamlValueRef::String(value))))
                 }
             }
             _ => {
                 // キーまたは値の読み取り
-                let _start_pos = self.position;
-                
-                // コロンがあるかチェック
-                let mut temp_pos = self.position;
-                let mut found_colon = false;
-                
-                while temp_pos < self.input.len() {
-                    match self.input[temp_pos] {
-                        ':' => {
-                            // コロンの後にスペースまたは改行があるかチェック
-                            if temp_pos + 1 < self.input.len() {
-                                match self.input[temp_pos + 1] {
-                                    ' ' | '\t' | '\n' | '\r' => {
-                                        found_colon = true;
-                                        break;
-                                    }
-                                    _ => {}
-                                }
-                            } else {
-                                found_colon = true;
-                                break;
-                            }
-                        }
-                        '\n' | '#' => break,
-                        _ => {}
-                    }
-                    temp_pos += 1;
-                }
+                // コロン（後にスペースまたは行末が続くもの）があるかチェック
+                let end = self.comment_or_line_end();
+                let found_colon = has_key_indicator(&self.input[self.position..end]);
                 
                 if found_colon {
                     // キーとして読み取り
+                    self.node_column = Some(self.column - 1);
                     let key = self.read_key()?;
                     Ok(Some(Token::Key(key)))
                 } else {
                     // 値として読み取り
-                    let value = self.read_value()?;
-                    Ok(Some(Token::Value(value)))
+                    Ok(Some(self.read_value()?))
                 }
             }
         }
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>> {
-        let mut tokens = Vec::new();
-        let mut at_line_start = true;
+    pub fn tokenize(&mut self) -> Result<Vec<Token<'a>>> {
+        let tokens = self.tokenize_with_spans()?;
+        Ok(tokens.into_iter().map(|spanned| spanned.token).collect())
+    }
 
-        while !self.is_at_end() {
-            // 行の開始時にインデント処理
-            if at_line_start {
-                let indent_tokens = self.handle_indentation()?;
-                tokens.extend(indent_tokens);
-                at_line_start = false;
+    /// Tokenize the input, recording where each token starts
+    pub fn tokenize_with_spans(&mut self) -> Result<Vec<SpannedToken<'a>>> {
+        self.by_ref().collect()
+    }
+
+    // 次の行またはトークンを読み、生成したトークンをキューに追加する
+    fn scan(&mut self) -> Result<()> {
+        while self.queue.is_empty() && !self.finished {
+            self.compact();
+
+            if self.is_at_end() {
+                // 残りのDEDENTトークンを生成
+                let span = self.span();
+                let dedents = self.close_indentation();
+                Self::push_all(&mut self.queue, dedents, span);
+                self.queue.push_back(SpannedToken { token: Token::Eof, span });
+                self.finished = true;
+                break;
             }
-            
+
+            // 行の開始時にインデント処理（フローコレクション内では無視）
+            if self.at_line_start && self.flow_depth == 0 {
+                let span = self.span();
+                if let Some(marker) = self.read_document_marker() {
+                    // ドキュメント境界ではインデントを0に戻す
+                    let dedents = self.close_indentation();
+                    Self::push_all(&mut self.queue, dedents, span);
+                    self.queue.push_back(SpannedToken { token: marker, span });
+                } else if self.current_char() == '%' {
+                    // ディレクティブもインデントを0に戻す
+                    let dedents = self.close_indentation();
+                    Self::push_all(&mut self.queue, dedents, span);
+                    let directive = self.read_directive();
+                    self.queue.push_back(SpannedToken { token: directive, span });
+                } else {
+                    let indent_tokens = self.handle_indentation()?;
+                    // インデントトークンは行の最初の文字を指す
+                    let span = self.span();
+                    Self::push_all(&mut self.queue, indent_tokens, span);
+                }
+                self.at_line_start = false;
+            }
+
             // 空白をスキップ（改行以外）
             self.skip_whitespace_except_newline();
-            
+
             if self.is_at_end() {
-                break;
+                continue;
             }
-            
+
+            // `- ` や `? ` の後から同じ行で始まるノードの列を記録する
+            if std::mem::take(&mut self.after_indicator) && !matches!(self.current_char(), '\n' | '#') {
+                self.compact_columns.push(self.column - 1);
+            }
+
             // 次のトークンを取得
+            let span = self.span();
             if let Some(token) = self.next_token()? {
-                let is_newline = matches!(token, Token::Newline);
-                tokens.push(token);
-                
-                if is_newline {
-                    at_line_start = true;
+                match token {
+                    Token::Newline => {
+                        self.at_line_start = true;
+                        self.node_column = None;
+                    }
+                    Token::ListItem | Token::ExplicitKey if self.flow_depth == 0 => self.after_indicator = true,
+                    _ => {}
                 }
+                self.queue.push_back(SpannedToken { token, span });
             }
         }
-        
-        // 残りのDEDENTトークンを生成
-        while self.indent_stack.len() > 1 {
-            let indent_level = self.indent_stack.pop().unwrap();
-            tokens.push(Token::Dedent(indent_level));
+
+        Ok(())
+    }
+
+    fn push_all(tokens: &mut VecDeque<SpannedToken<'a>>, new_tokens: Vec<Token<'a>>, span: Span) {
+        tokens.extend(new_tokens.into_iter().map(|token| SpannedToken { token, span }));
+    }
+}
+
+// 行頭の `---` または `...`
+fn is_document_marker(line: &str) -> bool {
+    (line.starts_with("---") || line.starts_with("..."))
+        && matches!(line.as_bytes().get(3), None | Some(b' ' | b'\t' | b'\r'))
+}
+
+// 行内のコメントの開始位置。'#' は行頭か空白の後でのみコメントを始める
+// （`a#b` や `http://x/#frag` はスカラーの一部）
+fn find_comment(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    (0..bytes.len()).find(|&i| bytes[i] == b'#' && (i == 0 || matches!(bytes[i - 1], b' ' | b'\t')))
+}
+
+// 値の区切りの `:`（後に空白か行末が続くもの）を含むか
+fn has_key_indicator(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.iter().enumerate().any(|(i, &b)| {
+        b == b':' && matches!(bytes.get(i + 1), None | Some(b' ' | b'\t' | b'\r'))
+    })
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<SpannedToken<'a>>;
+
+    /// Return the next token; the last one is `Token::Eof`
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.scan();
+
+        // 読み込みエラーは、それによって生じた字句エラーより優先する
+        if let Some(error) = self.read_error.take() {
+            self.fail();
+            return Some(Err(error));
         }
-        
-        tokens.push(Token::Eof);
-        Ok(tokens)
+        if let Err(error) = result {
+            self.fail();
+            return Some(Err(error));
+        }
+
+        self.queue.pop_front().map(Ok)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::value::YamlValue;
 
     #[test]
     fn test_simple_key_value() {
@@ -354,9 +1245,9 @@ mod tests {
         let tokens = lexer.tokenize().unwrap();
         
         assert_eq!(tokens, vec![
-            Token::Key("key".to_string()),
+            Token::Key("key".into()),
             Token::Colon,
-            Token::Value(YamlValue::String("value".to_string())),
+            Token::Plain("value".into()),
             Token::Eof,
         ]);
     }
@@ -367,9 +1258,9 @@ mod tests {
         let tokens = lexer.tokenize().unwrap();
         
         // 期待されるトークン構造をテスト
-        assert!(tokens.contains(&Token::Key("parent".to_string())));
+        assert!(tokens.contains(&Token::Key("parent".into())));
         assert!(tokens.contains(&Token::Indent(2)));
-        assert!(tokens.contains(&Token::Key("child".to_string())));
+        assert!(tokens.contains(&Token::Key("child".into())));
     }
 
     #[test]
@@ -378,8 +1269,8 @@ mod tests {
         let tokens = lexer.tokenize().unwrap();
         
         assert!(tokens.contains(&Token::ListItem));
-        assert!(tokens.contains(&Token::Value(YamlValue::String("item1".to_string()))));
-        assert!(tokens.contains(&Token::Value(YamlValue::String("item2".to_string()))));
+        assert!(tokens.contains(&Token::Plain("item1".into())));
+        assert!(tokens.contains(&Token::Plain("item2".into())));
     }
 
     #[test]
@@ -387,11 +1278,11 @@ mod tests {
         let mut lexer = Lexer::new("int: 42\nfloat: 3.14\nbool: true\nnull: null");
         let tokens = lexer.tokenize().unwrap();
         
-        // 各値の型が正しく判定されることを確認
-        assert!(tokens.iter().any(|t| matches!(t, Token::Value(YamlValue::Integer(42)))));
-        assert!(tokens.iter().any(|t| matches!(t, Token::Value(YamlValue::Float(f)) if (f - 3.14).abs() < f64::EPSILON)));
-        assert!(tokens.iter().any(|t| matches!(t, Token::Value(YamlValue::Boolean(true)))));
-        assert!(tokens.iter().any(|t| matches!(t, Token::Value(YamlValue::Null))));
+        // プレーンスカラーは型を決めずにそのまま渡す
+        assert!(tokens.contains(&Token::Plain("42".into())));
+        assert!(tokens.contains(&Token::Plain("3.14".into())));
+        assert!(tokens.contains(&Token::Plain("true".into())));
+        assert!(tokens.contains(&Token::Plain("null".into())));
     }
 
     #[test]
@@ -399,7 +1290,84 @@ mod tests {
         let mut lexer = Lexer::new(r#"quoted: "hello world""#);
         let tokens = lexer.tokenize().unwrap();
         
-        assert!(tokens.iter().any(|t| matches!(t, Token::Value(YamlValue::String(s)) if s == "hello world")));
+        assert!(tokens.iter().any(|t| matches!(t, Token::Value(YamlValueRef::String(s)) if s == "hello world")));
+    }
+
+    fn quoted(input: &str) -> Result<String> {
+        let tokens = Lexer::new(input).tokenize()?;
+        Ok(tokens
+            .into_iter()
+            .find_map(|t| match t {
+                Token::Value(YamlValueRef::String(s)) => Some(s.into_owned()),
+                _ => None,
+            })
+            .unwrap())
+    }
+
+    #[test]
+    fn test_double_quoted_escapes() {
+        assert_eq!(
+            quoted(r#"s: "\0\a\b\t\	\n\v\f\r\e\ \"\/\\""#).unwrap(),
+            "\0\u{7}\u{8}\t\t\n\u{b}\u{c}\r\u{1b} \"/\\"
+        );
+        assert_eq!(quoted(r#"s: "\N\_\L\P""#).unwrap(), "\u{85}\u{a0}\u{2028}\u{2029}");
+        assert_eq!(quoted(r#"s: "\x41\u00e9\U0001F600 caf\u00E9""#).unwrap(), "Aé😀 café");
+    }
+
+    #[test]
+    fn test_single_quoted_escapes() {
+        assert_eq!(quoted("s: 'it''s'").unwrap(), "it's");
+        assert_eq!(quoted("s: ''''").unwrap(), "'");
+        assert_eq!(quoted(r"s: 'a\nb'").unwrap(), r"a\nb");
+    }
+
+    #[test]
+    fn test_multiline_quoted_strings() {
+        assert_eq!(
+            quoted("s: \"folded \n  to a space,\t\n \n  to a line feed\"").unwrap(),
+            "folded to a space,\nto a line feed"
+        );
+        assert_eq!(quoted("s: 'a\r\n  b\n\n\n  c'").unwrap(), "a b\n\nc");
+        // エスケープされた改行は空白にならず、エスケープされた空白は残る
+        assert_eq!(quoted("s: \"a \\\n   b\\t\n c\"").unwrap(), "a b\t c");
+        assert_eq!(quoted("s: \"x\\ \n y\"").unwrap(), "x  y");
+
+        // 折り畳みの後も行と列を追跡する
+        let tokens = Lexer::new("a: \"x\n  y\"\nb: 1").tokenize_with_spans().unwrap();
+        let b = tokens.iter().find(|t| t.token == Token::Key("b".into())).unwrap();
+        assert_eq!((b.span.line, b.span.column), (3, 1));
+    }
+
+    #[test]
+    fn test_quoted_strings_borrow_when_unchanged() {
+        let tokens = Lexer::new("a: \"plain\"\nb: 'x y'\nc: 'it''s'").tokenize().unwrap();
+        let borrowed: Vec<_> = tokens
+            .into_iter()
+            .filter_map(|t| match t {
+                Token::Value(YamlValueRef::String(s)) => Some(matches!(s, Cow::Borrowed(_))),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(borrowed, vec![true, true, false]);
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        let error = |input: &str| match quoted(input) {
+            Err(YamlError::ParseError { message, span }) => (message, span.line, span.column),
+            other => panic!("expected error, got {:?}", other),
+        };
+        assert_eq!(error(r#"s: "ok \q""#), ("Invalid escape sequence: \\q".to_string(), 1, 8));
+        assert_eq!(
+            error("s:\n  - \"\\x4g\""),
+            ("Invalid escape sequence: \\x needs 2 hex digits".to_string(), 2, 6)
+        );
+        assert_eq!(
+            error(r#"s: "\uD800""#),
+            ("Invalid Unicode code point in escape sequence: D800".to_string(), 1, 5)
+        );
+        assert_eq!(error("s: \"abc\n").0, "Unterminated quoted string");
+        assert_eq!(error("s: 'abc''").0, "Unterminated quoted string");
     }
 
     #[test]
@@ -409,4 +1377,304 @@ mod tests {
         
         assert!(tokens.iter().any(|t| matches!(t, Token::Comment(c) if c == "this is a comment")));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_hash_inside_plain_scalar() {
+        let mut lexer = Lexer::new("a: b#c # note\nurl: http://x/#frag\nk#1:\n  - x\n    y#z");
+        let tokens = lexer.tokenize().unwrap();
+
+        // '#' は行頭か空白の後でのみコメントを始める
+        assert!(tokens.contains(&Token::Plain("b#c".into())));
+        assert!(tokens.contains(&Token::Comment("note".to_string())));
+        assert!(tokens.contains(&Token::Plain("http://x/#frag".into())));
+        assert!(tokens.contains(&Token::Key("k#1".into())));
+        assert!(tokens.contains(&Token::Plain("x y#z".into())));
+    }
+
+    #[test]
+    fn test_flow_tokens() {
+        let mut lexer = Lexer::new("tags: [a, {k: v}]");
+        let tokens = lexer.tokenize().unwrap();
+
+        assert_eq!(tokens, vec![
+            Token::Key("tags".into()),
+            Token::Colon,
+            Token::FlowStart('['),
+            Token::Plain("a".into()),
+            Token::FlowSeparator,
+            Token::FlowStart('{'),
+            Token::Key("k".into()),
+            Token::Colon,
+            Token::Plain("v".into()),
+            Token::FlowEnd('}'),
+            Token::FlowEnd(']'),
+            Token::Eof,
+        ]);
+    }
+
+    #[test]
+    fn test_multiline_flow_ignores_indentation() {
+        let mut lexer = Lexer::new("tags: [\n  a,\n    b\n]\nnext: 1");
+        let tokens = lexer.tokenize().unwrap();
+
+        // フロー内の改行はインデントトークンを生成しない
+        assert!(!tokens.iter().any(|t| matches!(t, Token::Indent(_) | Token::Dedent(_))));
+        assert!(tokens.contains(&Token::Key("next".into())));
+    }
+
+    fn block_scalar(input: &str) -> YamlValue {
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.tokenize().unwrap();
+        tokens
+            .into_iter()
+            .find_map(|t| match t {
+                Token::Value(v) => Some(v.into_owned()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_literal_block_scalar() {
+        assert_eq!(
+            block_scalar("script: |\n  echo a\n  if x; then\n    y\n  fi\nnext: 1"),
+            YamlValue::String("echo a\nif x; then\n  y\nfi\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_folded_block_scalar() {
+        assert_eq!(
+            block_scalar("s: >\n  a\n  b\n\n  c\n    d\n  e\n"),
+            YamlValue::String("a b\nc\n  d\ne\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_block_scalar_chomping() {
+        assert_eq!(block_scalar("s: |-\n  a\n\n\n"), YamlValue::String("a".to_string()));
+        assert_eq!(block_scalar("s: |\n  a\n\n\n"), YamlValue::String("a\n".to_string()));
+        assert_eq!(block_scalar("s: |+\n  a\n\n\n"), YamlValue::String("a\n\n\n".to_string()));
+        assert_eq!(block_scalar("s: >-\n  a\n  b\n"), YamlValue::String("a b".to_string()));
+    }
+
+    #[test]
+    fn test_block_scalar_indentation_indicator() {
+        assert_eq!(
+            block_scalar("s: |2\n    a\n  b\n"),
+            YamlValue::String("  a\nb\n".to_string())
+        );
+        assert_eq!(
+            block_scalar("s: |-1 # comment\n  a\n"),
+            YamlValue::String(" a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_block_scalar_header() {
+        let mut lexer = Lexer::new("s: | x");
+        match lexer.tokenize() {
+            Err(YamlError::UnexpectedChar { char, span }) => {
+                assert_eq!(char, 'x');
+                assert_eq!((span.line, span.column, span.offset), (1, 6, 5));
+            }
+            other => panic!("Expected UnexpectedChar, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let mut lexer = Lexer::new("café: 1\n  b: [x]");
+        let tokens = lexer.tokenize_with_spans().unwrap();
+
+        let span_of = |token: &Token| tokens.iter().find(|t| t.token == *token).unwrap().span;
+        assert_eq!(span_of(&Token::Colon), Span { line: 1, column: 5, offset: 5 });
+        assert_eq!(span_of(&Token::Indent(2)), Span { line: 2, column: 3, offset: 11 });
+        assert_eq!(span_of(&Token::FlowStart('[')), Span { line: 2, column: 6, offset: 14 });
+        assert_eq!(tokens.last().unwrap().token, Token::Eof);
+    }
+
+    #[test]
+    fn test_reserved_indicator() {
+        let mut lexer = Lexer::new("a: 1\nb: @x");
+        assert!(matches!(
+            lexer.tokenize(),
+            Err(YamlError::UnexpectedChar { char: '@', span: Span { line: 2, column: 4, .. } })
+        ));
+    }
+
+    #[test]
+    fn test_anchor_and_alias_tokens() {
+        let mut lexer = Lexer::new("a: &x 1\nb: [*x]");
+        let tokens = lexer.tokenize().unwrap();
+
+        assert!(tokens.contains(&Token::Anchor("x".to_string())));
+        assert!(tokens.contains(&Token::Alias("x".to_string())));
+        assert!(tokens.contains(&Token::FlowEnd(']')));
+    }
+
+    #[test]
+    fn test_document_markers() {
+        let mut lexer = Lexer::new("a:\n  b: 1\n---\nc: ---x\n...\n");
+        let tokens = lexer.tokenize().unwrap();
+
+        // `---` の前でインデントが閉じられる
+        let start = tokens.iter().position(|t| *t == Token::DocumentStart).unwrap();
+        assert_eq!(tokens[start - 1], Token::Dedent(2));
+        assert!(tokens.contains(&Token::DocumentEnd));
+        assert!(tokens.contains(&Token::Plain("---x".into())));
+    }
+
+    #[test]
+    fn test_tags_and_directives() {
+        let mut lexer = Lexer::new("%TAG !e! tag:example.com,2000: # comment\n---\na: !!str 1\nb: [!e!x y, !<tag:x.org:z> 2]\nc: !Ref\n");
+        let tokens = lexer.tokenize().unwrap();
+
+        // ハンドルはイベントパーサーが展開する
+        assert_eq!(tokens[0], Token::Directive("TAG !e! tag:example.com,2000:".to_string()));
+        assert!(tokens.contains(&Token::Tag("!!str".to_string())));
+        assert!(tokens.contains(&Token::Tag("!e!x".to_string())));
+        assert!(tokens.contains(&Token::Tag("!<tag:x.org:z>".to_string())));
+        assert!(tokens.contains(&Token::Tag("!Ref".to_string())));
+        assert!(tokens.contains(&Token::Plain("y".into())));
+
+        assert!(Lexer::new("a: !<tag:x 1").tokenize().is_err());
+        assert!(Lexer::new("a: !<> 1").tokenize().is_err());
+        // 逐語的タグの中身は URI の文字だけ（`!` は使える）
+        assert!(Lexer::new("a: !<tag:x y> 1").tokenize().is_err());
+        assert!(Lexer::new("a: !<tag:x%2> 1").tokenize().is_err());
+        assert!(Lexer::new("a: !<tag:x%20y> 1").tokenize().is_ok());
+        assert!(Lexer::new("a: !<!e!> 1").tokenize().is_ok());
+
+        // 短縮形の接尾辞も URI の文字だけ（`!` とフローの区切りは除く）
+        for input in ["a: !>b x", "a: !!\" x", "a: !e\\ x", "a: !a.b!c x", "a: !e!x!y z"] {
+            match Lexer::new(input).tokenize() {
+                Err(YamlError::ParseError { message, span }) => {
+                    assert_eq!(message, "Invalid character in tag", "{}", input);
+                    assert_eq!((span.line, span.column), (1, 4), "{}", input);
+                }
+                other => panic!("{}: {:?}", input, other),
+            }
+        }
+        assert!(Lexer::new("a: !a%21b x").tokenize().is_ok());
+        assert!(Lexer::new("a: !e! x").tokenize().is_ok());
+    }
+
+    #[test]
+    fn test_multiline_plain_scalars() {
+        let mut lexer = Lexer::new("a: one\n  two\n\n  three # c\nb:\n  x\n  y\nc: d\n");
+        let tokens = lexer.tokenize().unwrap();
+
+        assert_eq!(tokens[2], Token::Plain("one two\nthree".into()));
+        assert_eq!(tokens[3], Token::Comment("c".to_string()));
+        assert!(tokens.contains(&Token::Plain("x y".into())));
+        assert!(tokens.contains(&Token::Key("c".into())));
+
+        // 1行だけのスカラーは入力から借用する
+        let mut lexer = Lexer::new("a: one\nb: two\n");
+        let tokens = lexer.tokenize().unwrap();
+        assert!(matches!(&tokens[2], Token::Plain(Cow::Borrowed("one"))));
+
+        // キーの行やコメント行、同じ行の '-' より浅い行は続きではない
+        let mut lexer = Lexer::new("- - one\n  - two\n- three\n  # c\n  four: 4\n");
+        let tokens = lexer.tokenize().unwrap();
+        assert!(tokens.contains(&Token::Plain("one".into())));
+        assert!(tokens.contains(&Token::Plain("three".into())));
+        assert!(tokens.contains(&Token::Key("four".into())));
+    }
+
+    #[test]
+    fn test_dedent_to_unknown_level() {
+        match Lexer::new("a:\n    b: 1\n  c: 2\n").tokenize() {
+            Err(YamlError::IndentationError { span, .. }) => assert_eq!((span.line, span.column), (3, 3)),
+            other => panic!("Expected indentation error, got {:?}", other),
+        }
+        assert!(Lexer::new("- a:\n      x: 1\n    b: 2\n").tokenize().is_err());
+
+        // `- ` の後のキーの列へは戻れる
+        let tokens = Lexer::new("- a:\n      x: 1\n  b: 2\n").tokenize().unwrap();
+        assert!(tokens.ends_with(&[
+            Token::Dedent(6),
+            Token::Indent(2),
+            Token::Key("b".into()),
+            Token::Colon,
+            Token::Plain("2".into()),
+            Token::Newline,
+            Token::Dedent(2),
+            Token::Eof,
+        ]));
+    }
+
+    #[test]
+    fn test_explicit_and_quoted_keys() {
+        let mut lexer = Lexer::new("? a\n: b\n'q': 1\n");
+        let tokens = lexer.tokenize().unwrap();
+
+        assert_eq!(tokens, vec![
+            Token::ExplicitKey,
+            Token::Plain("a".into()),
+            Token::Newline,
+            Token::Colon,
+            Token::Plain("b".into()),
+            Token::Newline,
+            Token::QuotedKey("q".into()),
+            Token::Colon,
+            Token::Plain("1".into()),
+            Token::Newline,
+            Token::Eof,
+        ]);
+
+        let mut lexer = Lexer::new("{? x: y}");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[1], Token::ExplicitKey);
+        assert_eq!(tokens[2], Token::Key("x".into()));
+
+        // 空白が続かない '?' や '-' はスカラーの一部
+        let mut lexer = Lexer::new("?x: 1\n-1: 2\n");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[0], Token::Key("?x".into()));
+        assert_eq!(tokens[4], Token::Key("-1".into()));
+    }
+
+    #[test]
+    fn test_multibyte_text() {
+        let mut lexer = Lexer::new("キー: 値 # コメント\nlist: [ä, ö: ü]\n");
+        let tokens = lexer.tokenize().unwrap();
+
+        assert_eq!(tokens[0], Token::Key("キー".into()));
+        assert_eq!(tokens[2], Token::Plain("値".into()));
+        assert_eq!(tokens[3], Token::Comment("コメント".to_string()));
+        assert!(tokens.contains(&Token::Key("ö".into())));
+        assert!(tokens.contains(&Token::Plain("ü".into())));
+    }
+
+    // 1バイトずつ返すリーダー（マルチバイト文字がチャンク境界で分断される）
+    fn byte_reader(input: &[u8]) -> io::BufReader<&[u8]> {
+        io::BufReader::with_capacity(1, input)
+    }
+
+    #[test]
+    fn test_reader_matches_str() {
+        let mut input = String::from("名前: café\ns: |\n  ✓ done\nf: 折り\n  返し\n\n  ✓\n");
+        for i in 0..1000 {
+            input.push_str(&format!("k{}: [é, {}]\n", i, i));
+        }
+
+        let expected = Lexer::new(&input).tokenize_with_spans().unwrap();
+        let tokens = Lexer::from_reader(byte_reader(input.as_bytes())).tokenize_with_spans().unwrap();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_reader_invalid_utf8() {
+        let mut lexer = Lexer::from_reader(byte_reader(b"a: \xff\n"));
+        match lexer.tokenize() {
+            Err(YamlError::Io(error)) => assert_eq!(error.kind(), io::ErrorKind::InvalidData),
+            other => panic!("Expected I/O error, got {:?}", other),
+        }
+
+        // 末尾で途切れたシーケンス
+        let mut lexer = Lexer::from_reader(byte_reader(&"a: é".as_bytes()[..4]));
+        assert!(matches!(lexer.tokenize(), Err(YamlError::Io(_))));
+    }
+}
@@ -7,9 +7,21 @@
 //! 
 //! - Parse YAML into generic `YamlValue` enum
 //! - Deserialize directly to custom structs with `YamlDeserialize` trait
+//! - Write values and structs back to YAML with `to_yaml_string` and `YamlSerialize`
 //! - Support for nested structures, arrays, and optional fields
+//! - Mappings keep the key order of the document and accept any value as a key (`200: OK`, `? [a, b]`)
+//! - `value["key"][0]` indexing, `get` / `get_mut` and JSON Pointer lookup (`pointer`)
+//! - Typed accessors (`as_str`, `as_i64`, ...) and `From` / `FromIterator` conversions
+//! - `yaml!` macro for building values from JSON-like literals
 //! - Convenient macros for field extraction
-//! - Comprehensive error handling
+//! - `#[derive(YamlDeserialize)]` with the `derive` feature
+//! - `serde` support with the `serde` feature (`from_str` / `from_value`, `to_string` / `to_value`)
+//! - Errors with line/column positions and a caret-annotated snippet renderer
+//! - Pull-style event API (`parse_yaml_events`) for streaming over large documents
+//! - Incremental parsing from any `std::io::Read` (`parse_yaml_reader`, `parse_yaml_documents_reader`)
+//! - Tags: standard `!!` tags, `%TAG` directives, `YamlValue::Tagged` and custom tag resolvers
+//! - Failsafe, JSON, YAML 1.2 core and YAML 1.1 schemas for resolving plain scalars (`parse_yaml_with_schema`)
+//! - Zero-copy parsing into `YamlValueRef`, which borrows strings from the input (`parse_yaml_borrowed`)
 //! 
 //! # Basic Usage
 //! 
@@ -59,18 +71,41 @@
 
 pub mod error;
 pub mod value;
+pub mod index;
+pub mod mapping;
 pub mod token;
 pub mod lexer;
+pub mod schema;
+pub mod event;
 pub mod parser;
 pub mod deserialize;
+pub mod serialize;
+pub mod emitter;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "serde")]
+pub mod ser;
 
 // Re-export the main types and functions
 pub use error::{YamlError, Result};
-pub use value::YamlValue;
-pub use token::Token;
+pub use value::{YamlValue, YamlValueRef};
+pub use mapping::Mapping;
+pub use token::{Span, SpannedToken, Token};
 pub use lexer::Lexer;
-pub use parser::Parser;
+pub use schema::Schema;
+pub use event::{Event, Events, SpannedEvent};
+pub use parser::{Documents, Parser};
 pub use deserialize::YamlDeserialize;
+#[cfg(feature = "derive")]
+pub use yaml_parser_derive::YamlDeserialize;
+pub use serialize::YamlSerialize;
+#[cfg(feature = "serde")]
+pub use de::{from_str, from_value};
+#[cfg(feature = "serde")]
+pub use ser::{to_string, to_value};
+pub use emitter::{Emitter, EmitterOptions, QuoteStyle};
+
+use std::io::{BufReader, Read};
 
 /// Parse a YAML string into a YamlValue
 /// 
@@ -91,14 +126,266 @@ pub use deserialize::YamlDeserialize;
 /// let result = parse_yaml(yaml).unwrap();
 /// ```
 pub fn parse_yaml(input: &str) -> Result<YamlValue> {
-    let mut lexer = Lexer::new(input);
-    let tokens = lexer.tokenize()?;
-    let mut parser = Parser::new(tokens);
+    let mut parser = Parser::from_lexer(Lexer::new(input));
+    parser.parse()
+}
+
+/// Parse a YAML string into a YamlValue, resolving plain scalars with `schema`
+/// 
+/// `parse_yaml` uses the YAML 1.2 core schema. Quoted and block scalars are
+/// always strings.
+/// 
+/// # Arguments
+/// 
+/// * `input` - A string slice containing the YAML to parse
+/// * `schema` - The schema that decides the type of plain scalars
+/// 
+/// # Returns
+/// 
+/// Returns a `Result<YamlValue>` containing the parsed YAML structure or an error
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use yaml_parser::{parse_yaml_with_schema, Schema, YamlValue};
+/// 
+/// let yaml = "enabled: yes\nmode: 0o17";
+/// let value = parse_yaml_with_schema(yaml, Schema::Yaml11).unwrap();
+/// assert_eq!(value["enabled"], YamlValue::Boolean(true));
+/// assert_eq!(value["mode"], YamlValue::String("0o17".to_string()));
+/// ```
+pub fn parse_yaml_with_schema(input: &str, schema: Schema) -> Result<YamlValue> {
+    let mut parser = Parser::from_lexer(Lexer::new(input)).with_schema(schema);
+    parser.parse()
+}
+
+/// Parse a YAML string into a YamlValueRef that borrows from `input`
+/// 
+/// Plain and quoted scalars without escape sequences are not copied; escaped
+/// and block scalars own their text. Use `YamlValueRef::into_owned` to get a
+/// `YamlValue`.
+/// 
+/// # Arguments
+/// 
+/// * `input` - A string slice containing the YAML to parse
+/// 
+/// # Returns
+/// 
+/// Returns a `Result<YamlValueRef>` whose strings live as long as `input`
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use std::borrow::Cow;
+/// use yaml_parser::{parse_yaml_borrowed, YamlValueRef};
+/// 
+/// let yaml = "path: /users\nmessage: \"hi\\n\"";
+/// let routes = parse_yaml_borrowed(yaml).unwrap();
+/// assert!(matches!(routes.get("path"), Some(YamlValueRef::String(Cow::Borrowed("/users")))));
+/// assert!(matches!(routes.get("message"), Some(YamlValueRef::String(Cow::Owned(_)))));
+/// ```
+pub fn parse_yaml_borrowed(input: &str) -> Result<YamlValueRef<'_>> {
+    let mut parser = Parser::from_lexer(Lexer::new(input));
+    parser.parse_borrowed()
+}
+
+/// Parse YAML read from `reader` into a YamlValue
+/// 
+/// The input is read in chunks as parsing proceeds instead of being loaded
+/// into memory up front. It must be UTF-8.
+/// 
+/// # Arguments
+/// 
+/// * `reader` - Any `std::io::Read` source, such as a file or socket
+/// 
+/// # Returns
+/// 
+/// Returns a `Result<YamlValue>`; read failures and invalid UTF-8 are reported as `YamlError::Io`
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use yaml_parser::parse_yaml_reader;
+/// 
+/// let file: &[u8] = b"name: John\nage: 30\n";
+/// let value = parse_yaml_reader(file).unwrap();
+/// assert_eq!(value, yaml_parser::parse_yaml("name: John\nage: 30").unwrap());
+/// ```
+pub fn parse_yaml_reader<R: Read>(reader: R) -> Result<YamlValue> {
+    let mut parser = Parser::from_lexer(Lexer::from_reader(BufReader::new(reader)));
     parser.parse()
 }
 
+/// Parse a multi-document YAML stream into one YamlValue per document
+/// 
+/// Documents are separated by `---` and may be terminated by `...`. Content
+/// before the first `---` forms an implicit first document.
+/// 
+/// # Arguments
+/// 
+/// * `input` - A string slice containing the YAML stream to parse
+/// 
+/// # Returns
+/// 
+/// Returns a `Result<Vec<YamlValue>>` containing every document in order, or the first error
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use yaml_parser::{parse_yaml_documents, YamlValue};
+/// 
+/// let yaml = "kind: Service\n---\nkind: Deployment\n...\n";
+/// let documents = parse_yaml_documents(yaml).unwrap();
+/// assert_eq!(documents.len(), 2);
+/// ```
+pub fn parse_yaml_documents(input: &str) -> Result<Vec<YamlValue>> {
+    parse_yaml_documents_iter(input).collect()
+}
+
+/// Parse a multi-document YAML stream, yielding documents one at a time
+/// 
+/// # Arguments
+/// 
+/// * `input` - A string slice containing the YAML stream to parse
+/// 
+/// # Returns
+/// 
+/// Returns a `Documents` iterator over `Result<YamlValue>`; iteration stops after the first error
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use yaml_parser::parse_yaml_documents_iter;
+/// 
+/// let yaml = "---\nname: a\n---\nname: b\n";
+/// for document in parse_yaml_documents_iter(yaml) {
+///     let document = document.unwrap();
+///     println!("{:?}", document);
+/// }
+/// ```
+pub fn parse_yaml_documents_iter(input: &str) -> Documents<'_> {
+    Parser::from_lexer(Lexer::new(input)).into_documents()
+}
+
+/// Parse a multi-document YAML stream from `reader`, yielding documents one at a time
+/// 
+/// Only the document being built is held in memory, so arbitrarily long
+/// streams (such as YAML-formatted logs) can be processed.
+/// 
+/// # Arguments
+/// 
+/// * `reader` - Any `std::io::Read` source, such as a file or socket
+/// 
+/// # Returns
+/// 
+/// Returns a `Documents` iterator over `Result<YamlValue>`; iteration stops after the first error
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use yaml_parser::parse_yaml_documents_reader;
+/// 
+/// let log: &[u8] = b"---\nlevel: info\n---\nlevel: warn\n";
+/// let count = parse_yaml_documents_reader(log).filter_map(Result::ok).count();
+/// assert_eq!(count, 2);
+/// ```
+pub fn parse_yaml_documents_reader<'a, R: Read + 'a>(reader: R) -> Documents<'a> {
+    Parser::from_lexer(Lexer::from_reader(BufReader::new(reader))).into_documents()
+}
+
+/// Parse a YAML stream into a sequence of events without building a tree
+/// 
+/// # Arguments
+/// 
+/// * `input` - A string slice containing the YAML stream to parse
+/// 
+/// # Returns
+/// 
+/// Returns an `Events` iterator over `Result<Event>`; iteration stops after the first error
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use yaml_parser::{parse_yaml_events, Event};
+/// 
+/// let yaml = "- a\n- b\n- c\n";
+/// let scalars = parse_yaml_events(yaml)
+///     .filter(|event| matches!(event, Ok(Event::Scalar { .. })))
+///     .count();
+/// assert_eq!(scalars, 3);
+/// ```
+pub fn parse_yaml_events(input: &str) -> Events<'_> {
+    Events::from_lexer(Lexer::new(input))
+}
+
+/// Serialize a YamlValue to YAML text using the default emitter options
+/// 
+/// Strings that would otherwise re-parse as booleans, numbers or null are
+/// quoted, so parsing the output yields a value equal to the input.
+/// 
+/// # Arguments
+/// 
+/// * `value` - The YamlValue to serialize
+/// 
+/// # Returns
+/// 
+/// Returns the YAML text, terminated by a newline
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use yaml_parser::{parse_yaml, to_yaml_string};
+/// 
+/// let value = parse_yaml("name: John\nzip: \"12345\"").unwrap();
+/// let yaml = to_yaml_string(&value);
+/// assert_eq!(yaml, "name: John\nzip: \"12345\"\n");
+/// assert_eq!(parse_yaml(&yaml).unwrap(), value);
+/// ```
+pub fn to_yaml_string(value: &YamlValue) -> String {
+    Emitter::default().emit(value)
+}
+
+/// Serialize a type that implements YamlSerialize to YAML text
+/// 
+/// # Arguments
+/// 
+/// * `value` - The value to serialize
+/// 
+/// # Returns
+/// 
+/// Returns the YAML text, terminated by a newline
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use yaml_parser::{to_yaml_string_from, Mapping, YamlSerialize, YamlValue};
+/// 
+/// struct Person {
+///     name: String,
+///     age: i64,
+/// }
+/// 
+/// impl YamlSerialize for Person {
+///     fn to_yaml(&self) -> YamlValue {
+///         let mut map = Mapping::new();
+///         map.insert("name".to_string(), self.name.to_yaml());
+///         map.insert("age".to_string(), self.age.to_yaml());
+///         YamlValue::Object(map)
+///     }
+/// }
+/// 
+/// let person = Person { name: "John".to_string(), age: 30 };
+/// assert_eq!(to_yaml_string_from(&person), "name: John\nage: 30\n");
+/// ```
+pub fn to_yaml_string_from<T: YamlSerialize>(value: &T) -> String {
+    to_yaml_string(&value.to_yaml())
+}
+
 /// Parse a YAML string directly into a type that implements YamlDeserialize
 /// 
+/// Deserialization errors report the path of the offending node and its
+/// position in `input`.
+/// 
 /// # Arguments
 /// 
 /// * `input` - A string slice containing the YAML to parse
@@ -130,8 +417,9 @@ pub fn parse_yaml(input: &str) -> Result<YamlValue> {
 /// let person: Person = parse_yaml_to(yaml).unwrap();
 /// ```
 pub fn parse_yaml_to<T: YamlDeserialize>(input: &str) -> Result<T> {
-    let yaml_value = parse_yaml(input)?;
-    T::from_yaml(&yaml_value)
+    let mut parser = Parser::from_lexer(Lexer::new(input)).track_node_spans();
+    let yaml_value = parser.parse()?;
+    T::from_yaml(&yaml_value).map_err(|error| parser.locate(error))
 }
 
 /// Convert a YamlValue to a type that implements YamlDeserialize
@@ -0,0 +1,536 @@
+use crate::emitter::flow_text;
+use crate::value::{YamlValue, YamlValueRef};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A YAML mapping that remembers the order in which keys were inserted
+///
+/// Keys can be any [`YamlValue`]: strings, but also numbers (`200: OK`),
+/// `null` or collections (`? [a, b]`). Scalar keys are indexed, so looking
+/// them up by `&str`, `i64` or `bool` works like a `HashMap`; only collection
+/// and tagged keys are found by comparing them in order. Iteration follows
+/// document order.
+/// Two mappings are equal when they contain the same entries, regardless of
+/// order.
+///
+/// ```rust
+/// use yaml_parser::{parse_yaml, YamlValue};
+///
+/// let value = parse_yaml("name: api\n200: OK\n? [a, b]\n: pair").unwrap();
+/// let map = value.as_object().unwrap();
+/// assert_eq!(map.get("name"), Some(&YamlValue::from("api")));
+/// assert_eq!(map.get(&200), Some(&YamlValue::from("OK")));
+/// assert_eq!(map.get(&YamlValue::from(vec!["a", "b"])), Some(&YamlValue::from("pair")));
+/// ```
+#[derive(Clone, Default)]
+pub struct Mapping {
+    entries: Vec<(YamlValue, YamlValue)>,
+    indices: HashMap<String, usize>, // 文字列のキー -> entries の位置
+    scalar_indices: HashMap<ScalarKey, usize>, // 他のスカラーのキー（コレクションのキーは線形探索）
+}
+
+// 文字列以外のスカラーのキーを索引に使う形。float はビット列で比べるため、
+// -0.0 は 0.0 にそろえ、どのキーとも等しくない NaN は索引しない
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ScalarKey {
+    Integer(i64),
+    Float(u64),
+    Boolean(bool),
+    Null,
+}
+
+impl ScalarKey {
+    pub(crate) fn of(key: &YamlValue) -> Option<Self> {
+        match key {
+            YamlValue::Integer(i) => Some(ScalarKey::Integer(*i)),
+            YamlValue::Float(f) => Self::float(*f),
+            YamlValue::Boolean(b) => Some(ScalarKey::Boolean(*b)),
+            YamlValue::Null => Some(ScalarKey::Null),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn of_ref(key: &YamlValueRef) -> Option<Self> {
+        match key {
+            YamlValueRef::Integer(i) => Some(ScalarKey::Integer(*i)),
+            YamlValueRef::Float(f) => Self::float(*f),
+            YamlValueRef::Boolean(b) => Some(ScalarKey::Boolean(*b)),
+            YamlValueRef::Null => Some(ScalarKey::Null),
+            _ => None,
+        }
+    }
+
+    fn float(f: f64) -> Option<Self> {
+        if f.is_nan() {
+            return None;
+        }
+        Some(ScalarKey::Float(if f == 0.0 { 0 } else { f.to_bits() }))
+    }
+}
+
+/// A type that can look up an entry of a [`Mapping`]
+///
+/// Implemented for string types, which match string keys, for `i64` and
+/// `bool`, and for [`YamlValue`], which matches a key of any kind.
+pub trait Key: private::Sealed {
+    #[doc(hidden)]
+    fn position_in(&self, map: &Mapping) -> Option<usize>;
+}
+
+impl Key for str {
+    fn position_in(&self, map: &Mapping) -> Option<usize> {
+        map.indices.get(self).copied()
+    }
+}
+
+impl Key for String {
+    fn position_in(&self, map: &Mapping) -> Option<usize> {
+        self.as_str().position_in(map)
+    }
+}
+
+impl Key for YamlValue {
+    fn position_in(&self, map: &Mapping) -> Option<usize> {
+        match (self, ScalarKey::of(self)) {
+            (YamlValue::String(s), _) => s.position_in(map),
+            (_, Some(key)) => map.scalar_indices.get(&key).copied(),
+            (key, None) => map.entries.iter().position(|(k, _)| k == key),
+        }
+    }
+}
+
+impl Key for i64 {
+    fn position_in(&self, map: &Mapping) -> Option<usize> {
+        map.scalar_indices.get(&ScalarKey::Integer(*self)).copied()
+    }
+}
+
+impl Key for bool {
+    fn position_in(&self, map: &Mapping) -> Option<usize> {
+        map.scalar_indices.get(&ScalarKey::Boolean(*self)).copied()
+    }
+}
+
+impl<T: Key + ?Sized> Key for &T {
+    fn position_in(&self, map: &Mapping) -> Option<usize> {
+        (**self).position_in(map)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl Sealed for super::YamlValue {}
+    impl Sealed for i64 {}
+    impl Sealed for bool {}
+    impl<T: Sealed + ?Sized> Sealed for &T {}
+}
+
+impl Mapping {
+    /// Create an empty mapping
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty mapping with room for `capacity` entries
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            indices: HashMap::with_capacity(capacity),
+            scalar_indices: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get<Q: Key + ?Sized>(&self, key: &Q) -> Option<&YamlValue> {
+        key.position_in(self).map(|i| &self.entries[i].1)
+    }
+
+    pub fn get_mut<Q: Key + ?Sized>(&mut self, key: &Q) -> Option<&mut YamlValue> {
+        let i = key.position_in(self)?;
+        Some(&mut self.entries[i].1)
+    }
+
+    pub fn contains_key<Q: Key + ?Sized>(&self, key: &Q) -> bool {
+        key.position_in(self).is_some()
+    }
+
+    // 文字列のキー、なければ表記が text と同じスカラーのキー（`200:` の 200 など）の値
+    pub(crate) fn get_by_text(&self, text: &str) -> Option<&YamlValue> {
+        let i = text.position_in(self).or_else(|| self.text_position(text))?;
+        Some(&self.entries[i].1)
+    }
+
+    pub(crate) fn get_by_text_mut(&mut self, text: &str) -> Option<&mut YamlValue> {
+        let i = text.position_in(self).or_else(|| self.text_position(text))?;
+        Some(&mut self.entries[i].1)
+    }
+
+    fn text_position(&self, text: &str) -> Option<usize> {
+        self.entries.iter().position(|(k, _)| {
+            matches!(k, YamlValue::Integer(_) | YamlValue::Float(_) | YamlValue::Boolean(_) | YamlValue::Null)
+                && key_text(k) == text
+        })
+    }
+
+    /// Insert a value, returning the previous one
+    ///
+    /// A new key goes to the end; replacing an existing key keeps its position.
+    pub fn insert(&mut self, key: impl Into<YamlValue>, value: YamlValue) -> Option<YamlValue> {
+        let key = key.into();
+        match key.position_in(self) {
+            Some(i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                if let YamlValue::String(s) = &key {
+                    self.indices.insert(s.clone(), self.entries.len());
+                } else if let Some(scalar) = ScalarKey::of(&key) {
+                    self.scalar_indices.insert(scalar, self.entries.len());
+                }
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Remove a key, shifting later entries to keep their order
+    pub fn remove<Q: Key + ?Sized>(&mut self, key: &Q) -> Option<YamlValue> {
+        let i = key.position_in(self)?;
+        let (key, value) = self.entries.remove(i);
+        if let YamlValue::String(s) = &key {
+            self.indices.remove(s);
+        } else if let Some(scalar) = ScalarKey::of(&key) {
+            self.scalar_indices.remove(&scalar);
+        }
+        for index in self.indices.values_mut().chain(self.scalar_indices.values_mut()) {
+            if *index > i {
+                *index -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    /// Get the entry for `key` for in-place insertion
+    pub fn entry(&mut self, key: impl Into<YamlValue>) -> Entry<'_> {
+        Entry { map: self, key: key.into() }
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { inner: self.entries.iter() }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut { inner: self.entries.iter_mut() }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &YamlValue> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &YamlValue> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut YamlValue> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+        self.scalar_indices.clear();
+    }
+}
+
+// エラーのパスなどで使うキーの表記（文字列はそのまま、それ以外はフロー形式）
+pub(crate) fn key_text(key: &YamlValue) -> Cow<'_, str> {
+    match key {
+        YamlValue::String(s) => Cow::Borrowed(s),
+        other => Cow::Owned(flow_text(other)),
+    }
+}
+
+/// A view into a single entry of a [`Mapping`]
+pub struct Entry<'a> {
+    map: &'a mut Mapping,
+    key: YamlValue,
+}
+
+impl<'a> Entry<'a> {
+    pub fn key(&self) -> &YamlValue {
+        &self.key
+    }
+
+    /// Insert `default` if the key is missing and return the value
+    pub fn or_insert(self, default: YamlValue) -> &'a mut YamlValue {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> YamlValue) -> &'a mut YamlValue {
+        let i = match self.key.position_in(self.map) {
+            Some(i) => i,
+            None => {
+                self.map.insert(self.key, default());
+                self.map.entries.len() - 1
+            }
+        };
+        &mut self.map.entries[i].1
+    }
+}
+
+/// Iterator over the entries of a [`Mapping`] in insertion order
+pub struct Iter<'a> {
+    inner: std::slice::Iter<'a, (YamlValue, YamlValue)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a YamlValue, &'a YamlValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, v)| (k, v))
+    }
+}
+
+/// Mutable iterator over the entries of a [`Mapping`] in insertion order
+pub struct IterMut<'a> {
+    inner: std::slice::IterMut<'a, (YamlValue, YamlValue)>,
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a YamlValue, &'a mut YamlValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (&*k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+/// Owning iterator over the entries of a [`Mapping`] in insertion order
+pub struct IntoIter {
+    inner: std::vec::IntoIter<(YamlValue, YamlValue)>,
+}
+
+impl Iterator for IntoIter {
+    type Item = (YamlValue, YamlValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl IntoIterator for Mapping {
+    type Item = (YamlValue, YamlValue);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter { inner: self.entries.into_iter() }
+    }
+}
+
+impl<'a> IntoIterator for &'a Mapping {
+    type Item = (&'a YamlValue, &'a YamlValue);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Mapping {
+    type Item = (&'a YamlValue, &'a mut YamlValue);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a> {
+        self.iter_mut()
+    }
+}
+
+impl<K: Into<YamlValue>> FromIterator<(K, YamlValue)> for Mapping {
+    fn from_iter<I: IntoIterator<Item = (K, YamlValue)>>(iter: I) -> Self {
+        let mut map = Mapping::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Into<YamlValue>> Extend<(K, YamlValue)> for Mapping {
+    fn extend<I: IntoIterator<Item = (K, YamlValue)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Into<YamlValue>, const N: usize> From<[(K, YamlValue); N]> for Mapping {
+    fn from(entries: [(K, YamlValue); N]) -> Self {
+        entries.into_iter().collect()
+    }
+}
+
+// HashMap には順序がないため、変換後の順序はキーの昇順にする
+impl From<HashMap<String, YamlValue>> for Mapping {
+    fn from(map: HashMap<String, YamlValue>) -> Self {
+        let mut entries: Vec<_> = map.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.into_iter().collect()
+    }
+}
+
+/// Keys that are not strings are converted to their YAML text (`200`, `[a, b]`)
+impl From<Mapping> for HashMap<String, YamlValue> {
+    fn from(map: Mapping) -> Self {
+        map.into_iter()
+            .map(|(key, value)| (key_text(&key).into_owned(), value))
+            .collect()
+    }
+}
+
+// 順序に関係なく、同じエントリを持つマッピングは等しい
+impl PartialEq for Mapping {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl fmt::Debug for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Mapping {
+        let mut map = Mapping::new();
+        map.insert("zebra".to_string(), YamlValue::Integer(1));
+        map.insert("apple".to_string(), YamlValue::Integer(2));
+        map.insert("mango".to_string(), YamlValue::Integer(3));
+        map
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut map = sample();
+        assert_eq!(map.keys().filter_map(YamlValue::as_str).collect::<Vec<_>>(), ["zebra", "apple", "mango"]);
+
+        // 既存キーの置き換えは位置を保つ
+        assert_eq!(map.insert("zebra".to_string(), YamlValue::Null), Some(YamlValue::Integer(1)));
+        assert_eq!(map.keys().filter_map(YamlValue::as_str).collect::<Vec<_>>(), ["zebra", "apple", "mango"]);
+        assert_eq!(map.get("zebra"), Some(&YamlValue::Null));
+    }
+
+    #[test]
+    fn test_remove_keeps_order() {
+        let mut map = sample();
+        assert_eq!(map.remove("zebra"), Some(YamlValue::Integer(1)));
+        assert_eq!(map.remove("zebra"), None);
+        assert_eq!(map.keys().filter_map(YamlValue::as_str).collect::<Vec<_>>(), ["apple", "mango"]);
+        assert_eq!(map.get("mango"), Some(&YamlValue::Integer(3)));
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut map = sample();
+        *map.entry("apple".to_string()).or_insert(YamlValue::Null) = YamlValue::Integer(20);
+        map.entry("kiwi".to_string()).or_insert(YamlValue::Integer(4));
+        assert_eq!(map.get("apple"), Some(&YamlValue::Integer(20)));
+        assert_eq!(map.keys().last().and_then(YamlValue::as_str), Some("kiwi"));
+    }
+
+    #[test]
+    fn test_non_string_keys() {
+        let mut map = sample();
+        map.insert(200, YamlValue::from("OK"));
+        map.insert(YamlValue::Null, YamlValue::Integer(0));
+        map.insert(vec!["a", "b"], YamlValue::Boolean(true));
+
+        assert_eq!(map.get(&200), Some(&YamlValue::from("OK")));
+        assert_eq!(map.get(&YamlValue::Null), Some(&YamlValue::Integer(0)));
+        assert_eq!(map.get(&YamlValue::from(vec!["a", "b"])), Some(&YamlValue::Boolean(true)));
+        // 文字列の "200" と整数の 200 は別のキー
+        assert_eq!(map.get("200"), None);
+
+        // 前のエントリを削除しても文字列キーの位置は正しい
+        assert_eq!(map.remove(&200), Some(YamlValue::from("OK")));
+        assert_eq!(map.get("mango"), Some(&YamlValue::Integer(3)));
+        assert_eq!(map.remove("zebra"), Some(YamlValue::Integer(1)));
+        assert_eq!(map.get(&YamlValue::Null), Some(&YamlValue::Integer(0)));
+        assert_eq!(map.len(), 4);
+
+        let text: std::collections::HashMap<String, YamlValue> = map.into();
+        assert_eq!(text.get("null"), Some(&YamlValue::Integer(0)));
+        assert_eq!(text.get("[a, b]"), Some(&YamlValue::Boolean(true)));
+    }
+
+    #[test]
+    fn test_scalar_keys_are_indexed() {
+        let mut map = Mapping::new();
+        for i in 0..1000 {
+            map.insert(i, YamlValue::Integer(i * 2));
+        }
+        map.insert(true, YamlValue::from("yes"));
+        map.insert(YamlValue::Float(0.0), YamlValue::from("zero"));
+        map.insert(YamlValue::Float(f64::NAN), YamlValue::from("nan"));
+        assert_eq!(map.len(), 1003);
+
+        assert_eq!(map.get(&999), Some(&YamlValue::Integer(1998)));
+        assert_eq!(map.get(&true), Some(&YamlValue::from("yes")));
+        // -0.0 は 0.0 と同じキー、NaN はどのキーとも等しくない
+        assert_eq!(map.get(&YamlValue::Float(-0.0)), Some(&YamlValue::from("zero")));
+        assert_eq!(map.get(&YamlValue::Float(f64::NAN)), None);
+
+        // 削除した位置より後のキーも見つかる
+        assert_eq!(map.remove(&10), Some(YamlValue::Integer(20)));
+        assert_eq!(map.get(&11), Some(&YamlValue::Integer(22)));
+        assert_eq!(map.get(&true), Some(&YamlValue::from("yes")));
+        assert_eq!(map.insert(500, YamlValue::Null), Some(YamlValue::Integer(1000)));
+        assert_eq!(map.len(), 1002);
+    }
+
+    #[test]
+    fn test_equality_ignores_order() {
+        let reversed: Mapping = sample().into_iter().rev().collect();
+        assert_eq!(reversed, sample());
+        assert_ne!(Mapping::new(), sample());
+    }
+}
@@ -1,401 +1,606 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
 
-use crate::error::{Result, YamlError};
-use crate::token::Token;
-use crate::value::YamlValue;
+use crate::error::{push_index, push_key, Result, YamlError};
+use crate::event::{Event, Events, SpannedEvent};
+use crate::lexer::Lexer;
+use crate::mapping::{key_text, Mapping, ScalarKey};
+use crate::schema::{Schema, YAML_TAG_PREFIX};
+use crate::token::{Span, SpannedToken, Token};
+use crate::value::{YamlValue, YamlValueRef};
 
-pub struct Parser {
-    tokens: Vec<Token>,
-    current: usize,
+/// Builds [`YamlValue`] trees from the events of an [`Events`] parser,
+/// resolving anchors, aliases and merge keys.
+///
+/// The `*_borrowed` methods build [`YamlValueRef`] trees instead, whose
+/// strings borrow from the input where the lexer could avoid a copy.
+///
+/// Nodes with a tag other than the standard `!!` types become
+/// [`YamlValue::Tagged`], unless a resolver is registered for the tag with
+/// [`Parser::with_tag_resolver`].
+pub struct Parser<'a> {
+    events: Events<'a>,
+    node_spans: Option<HashMap<String, Span>>, // ノードのパスと位置（有効な場合のみ）
+    path: String,                           // 構築中のノードのパス
+    tag_resolvers: HashMap<String, TagResolver<'a>>, // 展開済みのタグごとの変換関数
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+// タグ付きノードの値から結果の値を作る関数
+type TagResolver<'a> = Box<dyn Fn(YamlValue) -> Result<YamlValue> + 'a>;
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: Vec<Token<'a>>) -> Self {
+        Self::from_events(Events::new(tokens))
+    }
+
+    /// Create a parser from tokens produced by [`Lexer::tokenize_with_spans`],
+    /// so that errors report their source position.
+    ///
+    /// [`Lexer::tokenize_with_spans`]: crate::Lexer::tokenize_with_spans
+    pub fn with_spans(tokens: Vec<SpannedToken<'a>>) -> Self {
+        Self::from_events(Events::with_spans(tokens))
+    }
+
+    /// Create a parser that pulls tokens from `lexer` as it goes
+    pub fn from_lexer(lexer: Lexer<'a>) -> Self {
+        Self::from_events(Events::from_lexer(lexer))
+    }
+
+    /// Create a parser that builds values from an existing event stream
+    pub fn from_events(events: Events<'a>) -> Self {
         Self {
-            tokens,
-            current: 0,
+            events,
+            node_spans: None,
+            path: String::new(),
+            tag_resolvers: HashMap::new(),
         }
     }
 
-    fn is_at_end(&self) -> bool {
-        matches!(self.peek(), Token::Eof)
+    /// Resolve plain scalars with `schema` instead of the YAML 1.2 core schema
+    pub fn with_schema(self, schema: Schema) -> Self {
+        Self { events: self.events.with_schema(schema), ..self }
     }
 
-    fn peek(&self) -> &Token {
-        self.tokens.get(self.current).unwrap_or(&Token::Eof)
+    /// Build nodes tagged `tag` with `resolver` instead of wrapping them in
+    /// [`YamlValue::Tagged`]
+    ///
+    /// `tag` is compared after handle expansion: local tags as written
+    /// (`!Ref`), `!!name` for `tag:yaml.org,2002:name`, and any other tag in
+    /// full. The resolver receives the node's value, resolved as if it had
+    /// no tag. An error from the resolver stops parsing.
+    ///
+    /// ```rust
+    /// use yaml_parser::{Lexer, Parser, YamlValue};
+    ///
+    /// let yaml = "bucket: !Ref MyBucket\nname: !Upper web";
+    /// let mut parser = Parser::from_lexer(Lexer::new(yaml))
+    ///     .with_tag_resolver("!Upper", |value| {
+    ///         Ok(value.as_str().unwrap_or_default().to_uppercase().into())
+    ///     });
+    /// let value = parser.parse().unwrap();
+    ///
+    /// assert_eq!(value["name"], YamlValue::String("WEB".to_string()));
+    /// assert_eq!(value["bucket"].as_tagged(), Some(("!Ref", &YamlValue::String("MyBucket".to_string()))));
+    /// ```
+    pub fn with_tag_resolver(
+        mut self,
+        tag: &str,
+        resolver: impl Fn(YamlValue) -> Result<YamlValue> + 'a,
+    ) -> Self {
+        let tag = match tag.strip_prefix("!!") {
+            Some(name) => format!("{}{}", YAML_TAG_PREFIX, name),
+            None => tag.to_string(),
+        };
+        self.tag_resolvers.insert(tag, Box::new(resolver));
+        self
     }
 
-    #[allow(dead_code)]
-    fn peek_next(&self) -> Option<&Token> {
-        self.tokens.get(self.current + 1)
+    /// Record the source position of every node while parsing, so that
+    /// deserialization errors can be located with [`Parser::locate`].
+    pub fn track_node_spans(mut self) -> Self {
+        self.node_spans = Some(HashMap::new());
+        self
     }
 
-    fn previous(&self) -> &Token {
-        &self.tokens[self.current - 1]
+    /// Attach the source position of the offending node to an
+    /// `InvalidValue` error raised while deserializing the last parsed
+    /// document.
+    ///
+    /// Nodes without a recorded position (for example values copied in by an
+    /// alias or merge key) report the position of their nearest ancestor.
+    /// Other errors are returned unchanged.
+    pub fn locate(&self, error: YamlError) -> YamlError {
+        match error {
+            YamlError::InvalidValue { message, path, span: None } => {
+                let span = self.node_span(&path);
+                YamlError::InvalidValue { message, path, span }
+            }
+            other => other,
+        }
     }
 
-    fn advance(&mut self) -> &Token {
-        if !self.is_at_end() {
-            self.current += 1;
-        }
-        self.previous()
+    // パスに一致する（または最も近い祖先の）ノードの位置
+    fn node_span(&self, path: &str) -> Option<Span> {
+        self.node_spans
+            .as_ref()?
+            .iter()
+            .filter(|(prefix, _)| {
+                path.starts_with(prefix.as_str())
+                    && (prefix.is_empty()
+                        || prefix.len() == path.len()
+                        || matches!(path.as_bytes()[prefix.len()], b'.' | b'['))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, span)| *span)
     }
 
-    #[allow(dead_code)]
-    fn check(&self, token_type: &Token) -> bool {
-        if self.is_at_end() {
-            return false;
+    // 次のイベント。ストリームの途中で終わった場合は EOF エラー
+    fn next_event(&mut self) -> Result<SpannedEvent<'a>> {
+        match self.events.next_spanned() {
+            Some(event) => event,
+            None => Err(YamlError::UnexpectedEof { span: self.events.current_span() }),
         }
-        std::mem::discriminant(self.peek()) == std::mem::discriminant(token_type)
     }
 
-    #[allow(dead_code)]
-    fn consume_if(&mut self, check_fn: impl Fn(&Token) -> bool) -> bool {
-        if check_fn(self.peek()) {
-            self.advance();
-            true
-        } else {
-            false
+    fn unexpected_event(event: SpannedEvent<'a>) -> YamlError {
+        YamlError::ParseError {
+            message: format!("Unexpected event: {:?}", event.event),
+            span: event.span,
         }
     }
 
-    fn skip_newlines(&mut self) {
-        while matches!(self.peek(), Token::Newline | Token::Comment(_)) {
-            self.advance();
+    // 現在のパスのノード位置を記録する
+    fn record_node(&mut self, span: Span) {
+        if let Some(node_spans) = &mut self.node_spans {
+            node_spans.entry(self.path.clone()).or_insert(span);
         }
     }
 
+    /// Parse a single-document stream.
+    ///
+    /// A leading `---` and a trailing `...` are accepted; use
+    /// [`Parser::next_document`] for streams with more than one document.
     pub fn parse(&mut self) -> Result<YamlValue> {
-        self.skip_newlines();
-        
-        // トップレベルで複数のキーバリューペアがある場合はオブジェクトとして扱う
-        if matches!(self.peek(), Token::Key(_)) {
-            let mut map = HashMap::new();
-            
-            while !self.is_at_end() {
-                self.skip_newlines();
-                
-                if self.is_at_end() {
-                    break;
-                }
-                
-                // Keyがある場合のみ処理
-                if let Token::Key(key) = self.peek() {
-                    let key = key.clone();
-                    self.advance();
-                    
-                    // Colonを期待
-                    if !matches!(self.peek(), Token::Colon) {
-                        return Err(YamlError::ParseError("Expected ':' after key".to_string()));
-                    }
-                    self.advance();
-                    
-                    // 値をパース
-                    self.skip_newlines();
-                    
-                    let value = if matches!(self.peek(), Token::Indent(_)) {
-                        // ネストした構造
-                        self.advance(); // consume indent
-                        let nested = self.parse_value()?;
-                        
-                        // Consume dedent if present
-                        if matches!(self.peek(), Token::Dedent(_)) {
-                            self.advance();
-                        }
-                        
-                        nested
-                    } else if matches!(self.peek(), Token::Newline) {
-                        // 空の値の後にネストした内容
-                        self.advance();
-                        if matches!(self.peek(), Token::Indent(_)) {
-                            self.advance();
-                            let nested = self.parse_value()?;
-                            if matches!(self.peek(), Token::Dedent(_)) {
-                                self.advance();
-                            }
-                            nested
-                        } else {
-                            YamlValue::Null
-                        }
-                    } else {
-                        // シンプルな値
-                        match self.peek() {
-                            Token::Value(v) => {
-                                let val = v.clone();
-                                self.advance();
-                                val
-                            }
-                            Token::ListItem => {
-                                self.parse_array()?
-                            }
-                            _ => YamlValue::Null,
-                        }
-                    };
-                    
-                    map.insert(key, value);
-                } else {
-                    break;
+        self.parse_tree()
+    }
+
+    /// Like [`Parser::parse`], but returns a [`YamlValueRef`] borrowing
+    /// from the input.
+    pub fn parse_borrowed(&mut self) -> Result<YamlValueRef<'a>> {
+        self.parse_tree()
+    }
+
+    // 1つのドキュメントだけのストリームから N の木を構築する
+    fn parse_tree<N: TreeNode<'a>>(&mut self) -> Result<N> {
+        let value = match self.next_tree()? {
+            Some(value) => value,
+            None => return Err(YamlError::UnexpectedEof { span: self.events.current_span() }),
+        };
+
+        let event = self.next_event()?;
+        match event.event {
+            Event::StreamEnd => Ok(value),
+            _ => Err(YamlError::ParseError {
+                message: "Unexpected document start; use parse_yaml_documents for multi-document streams".to_string(),
+                span: event.span,
+            }),
+        }
+    }
+
+    /// Parse the next document of the stream, returning `None` once the
+    /// stream is exhausted.
+    pub fn next_document(&mut self) -> Result<Option<YamlValue>> {
+        self.next_tree()
+    }
+
+    /// Like [`Parser::next_document`], but returns a [`YamlValueRef`]
+    /// borrowing from the input.
+    pub fn next_document_borrowed(&mut self) -> Result<Option<YamlValueRef<'a>>> {
+        self.next_tree()
+    }
+
+    // 次のドキュメントの N の木を構築する
+    fn next_tree<N: TreeNode<'a>>(&mut self) -> Result<Option<N>> {
+        let mut event = match self.events.next_spanned() {
+            Some(event) => event?,
+            None => return Ok(None),
+        };
+        if event.event == Event::StreamStart {
+            event = self.next_event()?;
+        }
+
+        match event.event {
+            Event::StreamEnd => return Ok(None),
+            Event::DocumentStart { .. } => {}
+            _ => return Err(Self::unexpected_event(event)),
+        }
+
+        if let Some(node_spans) = &mut self.node_spans {
+            node_spans.clear();
+        }
+
+        let root = self.next_event()?;
+        let value = TreeBuilder::new(self).build_node(root)?;
+
+        let event = self.next_event()?;
+        match event.event {
+            Event::DocumentEnd { .. } => Ok(Some(value)),
+            _ => Err(Self::unexpected_event(event)),
+        }
+    }
+
+    /// Turn the parser into an iterator over the documents of the stream.
+    pub fn into_documents(self) -> Documents<'a> {
+        Documents { parser: Some(self) }
+    }
+}
+
+// 1つのドキュメントの木を構築する。アンカーはドキュメントごとに独立している
+struct TreeBuilder<'p, 'a, N> {
+    parser: &'p mut Parser<'a>,
+    anchors: HashMap<String, N>,      // 定義済みのアンカー
+    anchors_in_progress: Vec<String>, // 構築中のアンカー（循環検出用）
+}
+
+impl<'p, 'a, N: TreeNode<'a>> TreeBuilder<'p, 'a, N> {
+    fn new(parser: &'p mut Parser<'a>) -> Self {
+        Self { parser, anchors: HashMap::new(), anchors_in_progress: Vec::new() }
+    }
+
+    // パスに segment を追加して子ノードを構築する
+    fn build_child(&mut self, segment: impl FnOnce(&mut String), event: SpannedEvent<'a>) -> Result<N> {
+        if self.parser.node_spans.is_none() {
+            return self.build_node(event);
+        }
+
+        let len = self.parser.path.len();
+        segment(&mut self.parser.path);
+        let value = self.build_node(event);
+        self.parser.path.truncate(len);
+        value
+    }
+
+    // ノードの開始イベントから値を構築する
+    fn build_node(&mut self, event: SpannedEvent<'a>) -> Result<N> {
+        self.parser.record_node(event.span);
+
+        let span = event.span;
+        match event.event {
+            Event::Scalar { value, anchor, tag } => {
+                let value = self.apply_tag(tag, N::scalar(value), span)?;
+                if let Some(name) = anchor {
+                    self.anchors.insert(name, value.clone());
                 }
+                Ok(value)
             }
-            
-            Ok(YamlValue::Object(map))
-        } else {
-            // 単一の値またはリスト
-            let value = self.parse_value()?;
-            self.skip_newlines();
-            
-            // Dedentトークンをスキップ
-            while matches!(self.peek(), Token::Dedent(_)) {
-                self.advance();
-            }
-            
-            if !self.is_at_end() {
-                return Err(YamlError::ParseError("Unexpected content after document".to_string()));
-            }
-            
-            Ok(value)
+            Event::Alias(name) => self.resolve_alias(name, span),
+            Event::SequenceStart { anchor, tag } => self.build_anchored(anchor, |builder| {
+                let value = builder.build_sequence()?;
+                builder.apply_tag(tag, value, span)
+            }),
+            Event::MappingStart { anchor, tag } => self.build_anchored(anchor, |builder| {
+                let value = builder.build_mapping()?;
+                builder.apply_tag(tag, value, span)
+            }),
+            _ => Err(Parser::unexpected_event(event)),
         }
     }
 
-    fn parse_value(&mut self) -> Result<YamlValue> {
-        self.skip_newlines();
+    // タグ付きのノードを登録された関数で変換する（なければ Tagged で包む）
+    fn apply_tag(&self, tag: Option<String>, value: N, span: Span) -> Result<N> {
+        let tag = match tag {
+            Some(tag) => tag,
+            None => return Ok(value),
+        };
 
-        match self.peek() {
-            Token::Value(val) => {
-                let value = val.clone();
-                self.advance();
-                Ok(value)
+        let resolver = match self.parser.tag_resolvers.get(&tag) {
+            Some(resolver) => resolver,
+            None => return Ok(N::tagged(tag, value)),
+        };
+
+        match resolver(value.into_owned()) {
+            Ok(value) => Ok(N::from_owned(value)),
+            // 位置のないエラーにはタグ付きノードの位置を付ける
+            Err(YamlError::InvalidValue { message, path, span: None }) => {
+                Err(YamlError::InvalidValue { message, path, span: Some(span) })
             }
-            Token::Key(_) => self.parse_object(),
-            Token::ListItem => self.parse_array(),
-            Token::Eof => Err(YamlError::UnexpectedEof),
-            _ => Err(YamlError::ParseError(format!("Unexpected token: {:?}", self.peek()))),
+            Err(error) => Err(error),
         }
     }
 
-    fn parse_object(&mut self) -> Result<YamlValue> {
-        let mut map = HashMap::new();
-        let initial_indent = self.get_current_indent();
+    // アンカー（&name）が付いていれば、構築したノードを名前で登録する
+    fn build_anchored(&mut self, anchor: Option<String>, build: impl FnOnce(&mut Self) -> Result<N>) -> Result<N> {
+        let name = match anchor {
+            Some(name) => name,
+            None => return build(self),
+        };
 
-        loop {
-            self.skip_newlines();
+        self.anchors_in_progress.push(name.clone());
+        let value = build(self);
+        self.anchors_in_progress.pop();
 
-            // Check for dedent or end
-            if matches!(self.peek(), Token::Dedent(_) | Token::Eof) {
+        let value = value?;
+        self.anchors.insert(name, value.clone());
+        Ok(value)
+    }
+
+    fn resolve_alias(&self, name: String, span: Span) -> Result<N> {
+        if self.anchors_in_progress.contains(&name) {
+            return Err(YamlError::ParseError {
+                message: format!("Recursive alias: *{}", name),
+                span,
+            });
+        }
+
+        self.anchors.get(&name).cloned().ok_or_else(|| YamlError::ParseError {
+            message: format!("Undefined alias: *{}", name),
+            span,
+        })
+    }
+
+    fn build_sequence(&mut self) -> Result<N> {
+        let mut items = Vec::new();
+
+        loop {
+            let event = self.parser.next_event()?;
+            if event.event == Event::SequenceEnd {
                 break;
             }
 
-            // Check if we're at the same indent level
-            let current_indent = self.get_current_indent();
-            if current_indent < initial_indent {
+            let index = items.len();
+            items.push(self.build_child(|path| push_index(path, index), event)?);
+        }
+
+        Ok(N::sequence(items))
+    }
+
+    fn build_mapping(&mut self) -> Result<N> {
+        let mut map = N::Map::default();
+
+        loop {
+            let event = self.parser.next_event()?;
+            if event.event == Event::MappingEnd {
                 break;
             }
+            let key_span = event.span;
+            if is_merge_key(&event.event) {
+                let event = self.parser.next_event()?;
+                let value = self.build_child(|path| push_key(path, "<<"), event)?;
+                Self::merge(&mut map, value, key_span)?;
+                continue;
+            }
+            let key = self.build_node(event)?;
 
-            // Parse key
-            let key = match self.peek() {
-                Token::Key(k) => {
-                    let key = k.clone();
-                    self.advance();
-                    key
-                }
-                _ => break,
-            };
+            let event = self.parser.next_event()?;
+            let value = self.build_child(|path| push_key(path, &key.key_segment()), event)?;
+            N::insert(&mut map, key, value);
+        }
 
-            // Expect colon
-            if !matches!(self.peek(), Token::Colon) {
-                return Err(YamlError::ParseError("Expected ':' after key".to_string()));
+        Ok(N::mapping(map))
+    }
+
+    // マージキーの値（マッピングかその配列）のエントリを追加する。
+    // 既に存在するキー（明示的なキー）は上書きしない。
+    fn merge(map: &mut N::Map, value: N, key_span: Span) -> Result<()> {
+        for source in value.merge_sources() {
+            if let Err(other) = source.merge_into(map) {
+                return Err(YamlError::ParseError {
+                    message: format!("Merge key value must be a mapping, found {:?}", other),
+                    span: key_span,
+                });
             }
-            self.advance();
-
-            // Parse value
-            self.skip_newlines();
-            
-            let value = if matches!(self.peek(), Token::Indent(_)) {
-                // Nested structure
-                self.advance(); // consume indent
-                let nested = self.parse_value()?;
-                
-                // Consume dedent if present
-                if matches!(self.peek(), Token::Dedent(_)) {
-                    self.advance();
-                }
-                
-                nested
-            } else if matches!(self.peek(), Token::Newline) {
-                // Empty value before nested content
-                self.advance();
-                if matches!(self.peek(), Token::Indent(_)) {
-                    self.advance();
-                    let nested = self.parse_value()?;
-                    if matches!(self.peek(), Token::Dedent(_)) {
-                        self.advance();
-                    }
-                    nested
-                } else {
-                    YamlValue::Null
-                }
-            } else {
-                // Simple value
-                match self.peek() {
-                    Token::Value(v) => {
-                        let val = v.clone();
-                        self.advance();
-                        val
-                    }
-                    Token::ListItem => {
-                        self.parse_array()?
+        }
+        Ok(())
+    }
+}
+
+// 引用符のない `<<`（!!merge タグの付いたキー）
+fn is_merge_key(event: &Event<'_>) -> bool {
+    matches!(event, Event::Scalar { tag: Some(tag), .. }
+        if tag.strip_prefix(YAML_TAG_PREFIX) == Some("merge"))
+}
+
+// 構築する木のノード。`parse` は YamlValue を、`parse_borrowed` は
+// YamlValueRef を直接組み立てる（変換のための木のコピーを作らない）
+trait TreeNode<'a>: Clone + fmt::Debug {
+    type Map: Default;
+
+    fn scalar(value: YamlValueRef<'a>) -> Self;
+    fn sequence(items: Vec<Self>) -> Self;
+    fn mapping(map: Self::Map) -> Self;
+    fn tagged(tag: String, value: Self) -> Self;
+    fn from_owned(value: YamlValue) -> Self;
+    fn into_owned(self) -> YamlValue;
+    // パスに使うキーの表記（Mapping のキーと同じ）
+    fn key_segment(&self) -> Cow<'_, str>;
+    // 重複したキーは後の値で置き換え、位置は最初のものを保つ
+    fn insert(map: &mut Self::Map, key: Self, value: Self);
+    // マージキーの値の各要素（配列でなければ値そのもの）
+    fn merge_sources(self) -> Vec<Self>;
+    // マッピングのエントリのうち、まだないキーを追加する（マッピングでなければ Err）
+    fn merge_into(self, map: &mut Self::Map) -> std::result::Result<(), Self>;
+}
+
+impl<'a> TreeNode<'a> for YamlValue {
+    type Map = Mapping;
+
+    fn scalar(value: YamlValueRef<'a>) -> Self {
+        value.into_owned()
+    }
+
+    fn sequence(items: Vec<Self>) -> Self {
+        YamlValue::Array(items)
+    }
+
+    fn mapping(map: Mapping) -> Self {
+        YamlValue::Object(map)
+    }
+
+    fn tagged(tag: String, value: Self) -> Self {
+        YamlValue::Tagged(tag, Box::new(value))
+    }
+
+    fn from_owned(value: YamlValue) -> Self {
+        value
+    }
+
+    fn into_owned(self) -> YamlValue {
+        self
+    }
+
+    fn key_segment(&self) -> Cow<'_, str> {
+        key_text(self)
+    }
+
+    fn insert(map: &mut Mapping, key: Self, value: Self) {
+        map.insert(key, value);
+    }
+
+    fn merge_sources(self) -> Vec<Self> {
+        match self {
+            YamlValue::Array(items) => items,
+            other => vec![other],
+        }
+    }
+
+    fn merge_into(self, map: &mut Mapping) -> std::result::Result<(), Self> {
+        match self {
+            YamlValue::Object(entries) => {
+                for (k, v) in entries {
+                    if !map.contains_key(&k) {
+                        map.insert(k, v);
                     }
-                    _ => YamlValue::Null,
                 }
-            };
+                Ok(())
+            }
+            other => Err(other),
+        }
+    }
+}
+
+impl<'a> TreeNode<'a> for YamlValueRef<'a> {
+    type Map = MappingBuilder<'a>;
 
-            map.insert(key, value);
+    fn scalar(value: YamlValueRef<'a>) -> Self {
+        value
+    }
+
+    fn sequence(items: Vec<Self>) -> Self {
+        YamlValueRef::Array(items)
+    }
+
+    fn mapping(map: MappingBuilder<'a>) -> Self {
+        YamlValueRef::Object(map.entries)
+    }
+
+    fn tagged(tag: String, value: Self) -> Self {
+        YamlValueRef::Tagged(tag, Box::new(value))
+    }
+
+    fn from_owned(value: YamlValue) -> Self {
+        YamlValueRef::from(value)
+    }
+
+    fn into_owned(self) -> YamlValue {
+        YamlValueRef::into_owned(self)
+    }
+
+    fn key_segment(&self) -> Cow<'_, str> {
+        match self {
+            YamlValueRef::String(s) => Cow::Borrowed(s),
+            other => Cow::Owned(key_text(&other.clone().into_owned()).into_owned()),
         }
+    }
 
-        Ok(YamlValue::Object(map))
+    fn insert(map: &mut MappingBuilder<'a>, key: Self, value: Self) {
+        map.insert(key, value);
     }
 
-    fn parse_array(&mut self) -> Result<YamlValue> {
-        let mut array = Vec::new();
-        let initial_indent = self.get_current_indent();
+    fn merge_sources(self) -> Vec<Self> {
+        match self {
+            YamlValueRef::Array(items) => items,
+            other => vec![other],
+        }
+    }
 
-        while matches!(self.peek(), Token::ListItem) {
-            self.advance(); // consume '-'
-            
-            // Parse the value after the list item
-            let value = if matches!(self.peek(), Token::Newline) {
-                // Complex nested structure
-                self.advance();
-                if matches!(self.peek(), Token::Indent(_)) {
-                    self.advance();
-                    let nested = self.parse_value()?;
-                    if matches!(self.peek(), Token::Dedent(_)) {
-                        self.advance();
-                    }
-                    nested
-                } else {
-                    YamlValue::Null
+    fn merge_into(self, map: &mut MappingBuilder<'a>) -> std::result::Result<(), Self> {
+        match self {
+            YamlValueRef::Object(entries) => {
+                for (k, v) in entries {
+                    map.insert_absent(k, v);
                 }
-            } else {
-                // Simple value on same line
-                match self.peek() {
-                    Token::Value(v) => {
-                        let val = v.clone();
-                        self.advance();
-                        val
-                    }
-                    Token::Key(_) => {
-                        // リストアイテムと同じレベルのオブジェクト
-                        let mut map = HashMap::new();
-                        let _obj_indent = self.get_current_indent();
-                        
-                        loop {
-                            // keyがあるか確認
-                            if let Token::Key(key) = self.peek() {
-                                let key = key.clone();
-                                self.advance();
-                                
-                                // colonを期待
-                                if !matches!(self.peek(), Token::Colon) {
-                                    return Err(YamlError::ParseError("Expected ':' after key".to_string()));
-                                }
-                                self.advance();
-                                
-                                // 値をパース
-                                let value = match self.peek() {
-                                    Token::Value(v) => {
-                                        let val = v.clone();
-                                        self.advance();
-                                        val
-                                    }
-                                    _ => YamlValue::Null,
-                                };
-                                
-                                map.insert(key, value);
-                                
-                                // 次の行を確認
-                                if matches!(self.peek(), Token::Newline) {
-                                    self.advance();
-                                    
-                                    // インデントトークンをスキップ
-                                    if matches!(self.peek(), Token::Indent(_)) {
-                                        self.advance();
-                                    }
-                                    
-                                    // Dedentがあれば処理を終了
-                                    if matches!(self.peek(), Token::Dedent(_)) {
-                                        break;
-                                    }
-                                } else {
-                                    break;
-                                }
-                            } else {
-                                break;
-                            }
-                        }
-                        
-                        YamlValue::Object(map)
-                    }
-                    _ => YamlValue::Null,
-                }
-            };
-
-            array.push(value);
-            
-            self.skip_newlines();
-            
-            // Dedentトークンをスキップ
-            while matches!(self.peek(), Token::Dedent(_)) {
-                let dedent_level = if let Token::Dedent(level) = self.peek() {
-                    *level
-                } else {
-                    0
-                };
-                
-                // 配列のレベルより深いDedentのみスキップ
-                if dedent_level > initial_indent {
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
-            
-            // Check if we're still at the same indent level
-            let current_indent = self.get_current_indent();
-            if current_indent < initial_indent {
-                break;
-            }
-            
-            if matches!(self.peek(), Token::Eof) {
-                break;
+                Ok(())
             }
+            other => Err(other),
         }
+    }
+}
+
+// 構築中のマッピング。重複したキーは後の値で置き換え、位置は最初のものを保つ
+#[derive(Default)]
+struct MappingBuilder<'a> {
+    entries: Vec<(YamlValueRef<'a>, YamlValueRef<'a>)>,
+    indices: HashMap<Cow<'a, str>, usize>, // 文字列のキー -> entries の位置
+    scalar_indices: HashMap<ScalarKey, usize>, // 他のスカラーのキー（コレクションのキーは線形探索）
+}
 
-        Ok(YamlValue::Array(array))
+impl<'a> MappingBuilder<'a> {
+    fn position(&self, key: &YamlValueRef<'a>) -> Option<usize> {
+        match (key, ScalarKey::of_ref(key)) {
+            (YamlValueRef::String(s), _) => self.indices.get(s).copied(),
+            (_, Some(key)) => self.scalar_indices.get(&key).copied(),
+            (key, None) => self.entries.iter().position(|(k, _)| k == key),
+        }
     }
 
-    fn get_current_indent(&self) -> usize {
-        let mut indent_level = 0;
-        let mut indent_stack = vec![0];
-        
-        // トークンを最初から現在位置まで走査してインデントレベルを追跡
-        for i in 0..self.current {
-            match &self.tokens[i] {
-                Token::Indent(level) => {
-                    indent_stack.push(*level);
-                    indent_level = *level;
+    fn insert(&mut self, key: YamlValueRef<'a>, value: YamlValueRef<'a>) {
+        match self.position(&key) {
+            Some(i) => self.entries[i].1 = value,
+            None => {
+                if let YamlValueRef::String(s) = &key {
+                    self.indices.insert(s.clone(), self.entries.len());
+                } else if let Some(scalar) = ScalarKey::of_ref(&key) {
+                    self.scalar_indices.insert(scalar, self.entries.len());
                 }
-                Token::Dedent(_) => {
-                    if indent_stack.len() > 1 {
-                        indent_stack.pop();
-                        indent_level = *indent_stack.last().unwrap();
-                    }
-                }
-                _ => {}
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    // キーがまだなければ追加する（マージキー用）
+    fn insert_absent(&mut self, key: YamlValueRef<'a>, value: YamlValueRef<'a>) {
+        if self.position(&key).is_none() {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// Iterator over the documents of a multi-document stream.
+///
+/// Created by [`Parser::into_documents`] or
+/// [`parse_yaml_documents_iter`](crate::parse_yaml_documents_iter). Iteration
+/// stops after the first error.
+pub struct Documents<'a> {
+    parser: Option<Parser<'a>>,
+}
+
+impl Iterator for Documents<'_> {
+    type Item = Result<YamlValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parser = self.parser.as_mut()?;
+        match parser.next_document() {
+            Ok(Some(document)) => Some(Ok(document)),
+            Ok(None) => {
+                self.parser = None;
+                None
+            }
+            Err(error) => {
+                self.parser = None;
+                Some(Err(error))
             }
         }
-        
-        indent_level
     }
 }
 
@@ -403,11 +608,12 @@ impl Parser {
 mod tests {
     use super::*;
     use crate::lexer::Lexer;
+    use crate::yaml;
 
     fn parse_yaml_helper(input: &str) -> Result<YamlValue> {
         let mut lexer = Lexer::new(input);
-        let tokens = lexer.tokenize()?;
-        let mut parser = Parser::new(tokens);
+        let tokens = lexer.tokenize_with_spans()?;
+        let mut parser = Parser::with_spans(tokens);
         parser.parse()
     }
 
@@ -461,6 +667,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_parse_mixed_types() {
         let yaml = "string: hello\ninteger: 42\nfloat: 3.14\nboolean: true\nnull_value: null";
         let result = parse_yaml_helper(yaml).unwrap();
@@ -476,4 +683,539 @@ mod tests {
             _ => panic!("Expected object"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_flow_sequence() {
+        let yaml = "tags: [a, 'b c', \"d\", 42, true, null]";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        match result {
+            YamlValue::Object(map) => {
+                assert_eq!(map.get("tags"), Some(&YamlValue::Array(vec![
+                    YamlValue::String("a".to_string()),
+                    YamlValue::String("b c".to_string()),
+                    YamlValue::String("d".to_string()),
+                    YamlValue::Integer(42),
+                    YamlValue::Boolean(true),
+                    YamlValue::Null,
+                ])));
+            }
+            _ => panic!("Expected object"),
+        }
+    }
+
+    #[test]
+    fn test_parse_flow_mapping_nested() {
+        let yaml = "{name: x, ports: [80, 443], tls: {enabled: true}, empty}";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        match result {
+            YamlValue::Object(map) => {
+                assert_eq!(map.get("name"), Some(&YamlValue::String("x".to_string())));
+                assert_eq!(map.get("ports"), Some(&YamlValue::Array(vec![
+                    YamlValue::Integer(80),
+                    YamlValue::Integer(443),
+                ])));
+                match map.get("tls") {
+                    Some(YamlValue::Object(tls)) => {
+                        assert_eq!(tls.get("enabled"), Some(&YamlValue::Boolean(true)));
+                    }
+                    _ => panic!("Expected nested flow mapping"),
+                }
+                assert_eq!(map.get("empty"), Some(&YamlValue::Null));
+            }
+            _ => panic!("Expected object"),
+        }
+    }
+
+    #[test]
+    fn test_parse_multiline_flow_with_trailing_comma() {
+        let yaml = "tags: [\n  a,\n  b, # comment\n]\nnext: 1";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        match result {
+            YamlValue::Object(map) => {
+                assert_eq!(map.get("tags"), Some(&YamlValue::Array(vec![
+                    YamlValue::String("a".to_string()),
+                    YamlValue::String("b".to_string()),
+                ])));
+                assert_eq!(map.get("next"), Some(&YamlValue::Integer(1)));
+            }
+            _ => panic!("Expected object"),
+        }
+    }
+
+    #[test]
+    fn test_parse_flow_in_block_sequence() {
+        let yaml = "- [1, 2]\n- {a: b}\n- name: x\n  tags: [p, q]";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        match result {
+            YamlValue::Array(arr) => {
+                assert_eq!(arr.len(), 3);
+                assert_eq!(arr[0], YamlValue::Array(vec![YamlValue::Integer(1), YamlValue::Integer(2)]));
+                match &arr[2] {
+                    YamlValue::Object(item) => {
+                        assert_eq!(item.get("tags"), Some(&YamlValue::Array(vec![
+                            YamlValue::String("p".to_string()),
+                            YamlValue::String("q".to_string()),
+                        ])));
+                    }
+                    _ => panic!("Expected object item"),
+                }
+            }
+            _ => panic!("Expected array"),
+        }
+    }
+
+    #[test]
+    fn test_parse_unterminated_flow() {
+        assert!(matches!(parse_yaml_helper("tags: [a, b"), Err(YamlError::UnexpectedEof { .. })));
+        assert!(parse_yaml_helper("tags: [a, b}").is_err());
+        assert!(matches!(
+            parse_yaml_helper("tags: [a, b}"),
+            Err(YamlError::ParseError { span: Span { line: 1, column: 12, .. }, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_block_scalars_in_collections() {
+        let yaml = "jobs:\n  build: |\n    cargo build\n    cargo test\n  notes: >-\n    one\n    two\nsteps:\n  - |\n    x\n  - y";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        match result {
+            YamlValue::Object(map) => {
+                match map.get("jobs") {
+                    Some(YamlValue::Object(jobs)) => {
+                        assert_eq!(jobs.get("build"), Some(&YamlValue::String("cargo build\ncargo test\n".to_string())));
+                        assert_eq!(jobs.get("notes"), Some(&YamlValue::String("one two".to_string())));
+                    }
+                    _ => panic!("Expected nested object"),
+                }
+                assert_eq!(map.get("steps"), Some(&YamlValue::Array(vec![
+                    YamlValue::String("x\n".to_string()),
+                    YamlValue::String("y".to_string()),
+                ])));
+            }
+            _ => panic!("Expected object"),
+        }
+    }
+
+    #[test]
+    fn test_parse_anchor_and_alias() {
+        let yaml = "a: &x 1\nb: *x\nc: [*x, &y two, *y]";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        match result {
+            YamlValue::Object(map) => {
+                assert_eq!(map.get("b"), Some(&YamlValue::Integer(1)));
+                assert_eq!(map.get("c"), Some(&YamlValue::Array(vec![
+                    YamlValue::Integer(1),
+                    YamlValue::String("two".to_string()),
+                    YamlValue::String("two".to_string()),
+                ])));
+            }
+            _ => panic!("Expected object"),
+        }
+    }
+
+    #[test]
+    fn test_parse_merge_keys() {
+        let yaml = "defaults: &defaults\n  adapter: pg\n  host: localhost\ndev:\n  host: dev.local\n  <<: *defaults\nlist:\n  - <<: [*defaults, {port: 5432}]\n    adapter: mysql";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        match result {
+            YamlValue::Object(map) => {
+                match map.get("dev") {
+                    Some(YamlValue::Object(dev)) => {
+                        // 明示的なキーはマージされた値より優先される
+                        assert_eq!(dev.get("host"), Some(&YamlValue::String("dev.local".to_string())));
+                        assert_eq!(dev.get("adapter"), Some(&YamlValue::String("pg".to_string())));
+                        assert!(!dev.contains_key("<<"));
+                    }
+                    _ => panic!("Expected merged object"),
+                }
+                match map.get("list") {
+                    Some(YamlValue::Array(items)) => match &items[0] {
+                        YamlValue::Object(item) => {
+                            assert_eq!(item.get("adapter"), Some(&YamlValue::String("mysql".to_string())));
+                            assert_eq!(item.get("host"), Some(&YamlValue::String("localhost".to_string())));
+                            assert_eq!(item.get("port"), Some(&YamlValue::Integer(5432)));
+                        }
+                        _ => panic!("Expected object item"),
+                    },
+                    _ => panic!("Expected array"),
+                }
+            }
+            _ => panic!("Expected object"),
+        }
+
+        // 引用符付きの `<<` は普通のキー
+        assert_eq!(parse_yaml_helper("\"<<\": 1\n'<<': 2").unwrap(), yaml!({"<<": 2}));
+    }
+
+    #[test]
+    fn test_parse_alias_errors() {
+        assert!(matches!(parse_yaml_helper("a: *missing"), Err(YamlError::ParseError { message, .. }) if message.contains("Undefined alias")));
+        assert!(matches!(parse_yaml_helper("a: &x\n  b: *x"), Err(YamlError::ParseError { message, .. }) if message.contains("Recursive alias")));
+
+        // エラーはエイリアスの位置を指す
+        let error = parse_yaml_helper("a: 1\nb: *missing").unwrap_err();
+        assert_eq!(error.span().map(|span| (span.line, span.column)), Some((2, 4)));
+        assert!(parse_yaml_helper("<<: 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_nested_block_in_list_item_object() {
+        let yaml = "items:\n- name: A\n  team:\n    - J\n    - K\n  role: x\n- name: B\nother: 2";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        match result {
+            YamlValue::Object(map) => {
+                match map.get("items") {
+                    Some(YamlValue::Array(items)) => {
+                        assert_eq!(items.len(), 2);
+                        match &items[0] {
+                            YamlValue::Object(item) => {
+                                assert_eq!(item.get("team"), Some(&YamlValue::Array(vec![
+                                    YamlValue::String("J".to_string()),
+                                    YamlValue::String("K".to_string()),
+                                ])));
+                                assert_eq!(item.get("role"), Some(&YamlValue::String("x".to_string())));
+                            }
+                            _ => panic!("Expected object item"),
+                        }
+                    }
+                    _ => panic!("Expected array"),
+                }
+                assert_eq!(map.get("other"), Some(&YamlValue::Integer(2)));
+            }
+            _ => panic!("Expected object"),
+        }
+    }
+
+    fn parse_documents_helper(input: &str) -> Result<Vec<YamlValue>> {
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.tokenize()?;
+        Parser::new(tokens).into_documents().collect()
+    }
+
+    #[test]
+    fn test_parse_multiple_documents() {
+        let yaml = "a: 1\n---\n- x\n- y\n...\n--- |\n  text\n---\n";
+        let documents = parse_documents_helper(yaml).unwrap();
+
+        assert_eq!(documents.len(), 4);
+        assert!(matches!(&documents[0], YamlValue::Object(map) if map.get("a") == Some(&YamlValue::Integer(1))));
+        assert_eq!(documents[1], YamlValue::Array(vec![
+            YamlValue::String("x".to_string()),
+            YamlValue::String("y".to_string()),
+        ]));
+        assert_eq!(documents[2], YamlValue::String("text\n".to_string()));
+        assert_eq!(documents[3], YamlValue::Null);
+    }
+
+    #[test]
+    fn test_parse_empty_stream() {
+        assert_eq!(parse_documents_helper("").unwrap(), vec![]);
+        assert_eq!(parse_documents_helper("# comment\n").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_parse_single_document_markers() {
+        let result = parse_yaml_helper("---\nname: John\n...\n").unwrap();
+        assert!(matches!(result, YamlValue::Object(map) if map.get("name") == Some(&YamlValue::String("John".to_string()))));
+
+        assert!(parse_yaml_helper("a: 1\n---\nb: 2").is_err());
+    }
+
+    #[test]
+    fn test_anchors_are_scoped_to_document() {
+        assert!(parse_documents_helper("---\n&a x\n---\n*a").is_err());
+    }
+
+    #[test]
+    fn test_dedent_across_levels() {
+        // 複数レベルを一度に戻る場合もインデントの状態が正しく追跡される
+        let block = "a:\n  b:\n    - c: 1\n      d:\n        - x\n    - y\n  f: 3\ne: 2\n";
+        let flow = "{a: {b: [{c: 1, d: [x]}, y], f: 3}, e: 2}";
+        assert_eq!(parse_yaml_helper(block).unwrap(), parse_yaml_helper(flow).unwrap());
+    }
+
+    #[test]
+    fn test_documents_from_reader_are_lazy() {
+        // 2つ目のドキュメントの字句エラーは、そこまで読み進めたときに返る
+        let input: &[u8] = b"---\na: 1\n---\nb: @x\n";
+        let mut documents = Parser::from_lexer(Lexer::from_reader(input)).into_documents();
+
+        let first = documents.next().unwrap().unwrap();
+        assert_eq!(first, yaml!({"a": 1}));
+        assert!(matches!(documents.next(), Some(Err(YamlError::UnexpectedChar { char: '@', .. }))));
+        assert!(documents.next().is_none());
+    }
+
+    #[test]
+    fn test_parse_compact_nested_collections() {
+        let yaml = "- env:\n    - a\n  name: x\n- groups:\n  - b\n  name: y\n- - 1\n  - 2\n";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        assert_eq!(result, yaml!([
+            {"env": ["a"], "name": "x"},
+            {"groups": ["b"], "name": "y"},
+            [1, 2],
+        ]));
+    }
+
+    #[test]
+    fn test_parse_compact_sequences() {
+        // リストアイテムと同じ行から始まるシーケンス
+        assert_eq!(parse_yaml_helper("- - a\n  - b\n- c\n").unwrap(), yaml!([["a", "b"], "c"]));
+        assert_eq!(parse_yaml_helper("- - - x\n    - y\n  - z\n").unwrap(), yaml!([[["x", "y"], "z"]]));
+        assert_eq!(parse_yaml_helper("- - k: v\n    n: 1\n").unwrap(), yaml!([[{"k": "v", "n": 1}]]));
+
+        // `- key:` のキーと同じ列から始まるシーケンスはそのキーの値
+        assert_eq!(
+            parse_yaml_helper("- key:\n  - a\n  - b\n  other: 1\n- key:\n  - c\n- d\n").unwrap(),
+            yaml!([{"key": ["a", "b"], "other": 1}, {"key": ["c"]}, "d"])
+        );
+        // 同じ列のキーは兄弟のキー
+        assert_eq!(parse_yaml_helper("- key:\n  other: 1\n").unwrap(), yaml!([{"key": null, "other": 1}]));
+
+        // `- ` の後のノードより浅い行はエラー
+        assert!(parse_yaml_helper("- - a\n - b\n").is_err());
+        assert!(parse_yaml_helper("- a: 1\n b: 2\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_multiline_plain_scalars() {
+        let yaml = "description: This is a long\n  description that spans\n\n  several lines\nitems:\n  - first item\n    continued here\n  - second\nnext:\n    indented\n  value\n";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        assert_eq!(result, yaml!({
+            "description": "This is a long description that spans\nseveral lines",
+            "items": ["first item continued here", "second"],
+            "next": "indented value",
+        }));
+
+        // 続きの行があればスカラーは文字列になる
+        assert_eq!(parse_yaml_helper("top\n  1").unwrap(), YamlValue::String("top 1".to_string()));
+        assert_eq!(parse_yaml_helper("a: 1\n  2").unwrap(), yaml!({"a": "1 2"}));
+        assert!(parse_yaml_helper("a: one # c\n  two").is_err());
+    }
+
+    #[test]
+    fn test_parse_non_string_keys() {
+        let yaml = "200: OK\n404: Not Found\n'500': quoted\nnull: nothing\ntrue: yes\n";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        let YamlValue::Object(map) = &result else { panic!("Expected object") };
+        assert_eq!(map.get(&200), Some(&YamlValue::String("OK".to_string())));
+        assert_eq!(map.get(&404), Some(&YamlValue::String("Not Found".to_string())));
+        // 引用符付きのキーは文字列のまま
+        assert_eq!(map.get("500"), Some(&YamlValue::String("quoted".to_string())));
+        assert_eq!(map.get(&YamlValue::Null), Some(&YamlValue::String("nothing".to_string())));
+        assert_eq!(map.get(&true), Some(&YamlValue::String("yes".to_string())));
+
+        // Failsafe スキーマではキーも文字列
+        let failsafe = Parser::from_lexer(Lexer::new(yaml)).with_schema(Schema::Failsafe).parse().unwrap();
+        assert_eq!(failsafe["200"], YamlValue::String("OK".to_string()));
+
+        // ダッシュで始まるキー
+        let result = parse_yaml_helper("-1: neg\n2: pos\n-x: dash\n").unwrap();
+        let YamlValue::Object(map) = &result else { panic!("Expected object") };
+        assert_eq!(map.get(&-1), Some(&YamlValue::String("neg".to_string())));
+        assert_eq!(map.get(&2), Some(&YamlValue::String("pos".to_string())));
+        assert_eq!(map.get("-x"), Some(&YamlValue::String("dash".to_string())));
+
+        // フローマッピングのキーも同じ
+        let flow = parse_yaml_helper("{1: a, ~: b}").unwrap();
+        let YamlValue::Object(map) = &flow else { panic!("Expected object") };
+        assert_eq!(map.get(&1), Some(&YamlValue::String("a".to_string())));
+        assert_eq!(map.get(&YamlValue::Null), Some(&YamlValue::String("b".to_string())));
+    }
+
+    #[test]
+    fn test_parse_explicit_keys() {
+        let pair = |key: YamlValue, value: YamlValue| YamlValue::Object(Mapping::from([(key, value)]));
+
+        assert_eq!(parse_yaml_helper("? [a, b]\n: pair\n").unwrap(), pair(yaml!(["a", "b"]), yaml!("pair")));
+        assert_eq!(parse_yaml_helper("? - a\n  - b\n: - c\n").unwrap(), pair(yaml!(["a", "b"]), yaml!(["c"])));
+        assert_eq!(parse_yaml_helper("? a: 1\n: b\n").unwrap(), pair(yaml!({"a": 1}), yaml!("b")));
+        assert_eq!(parse_yaml_helper("? |\n  text\n: v\n").unwrap(), yaml!({"text\n": "v"}));
+        // 値のない明示的なキーは null
+        assert_eq!(parse_yaml_helper("? a\n? b\n: c\n").unwrap(), yaml!({"a": null, "b": "c"}));
+
+        // 普通のキーと混在でき、リストの項目でも使える
+        let result = parse_yaml_helper("outer:\n  ? [x]\n  : 1\n  k: v\nlist:\n  - ? a\n    : b\n    c: d\n").unwrap();
+        let YamlValue::Object(outer) = &result["outer"] else { panic!("Expected object") };
+        assert_eq!(outer.get(&yaml!(["x"])), Some(&YamlValue::Integer(1)));
+        assert_eq!(outer.get("k"), Some(&YamlValue::String("v".to_string())));
+        assert_eq!(result["list"], yaml!([{"a": "b", "c": "d"}]));
+
+        // フローの中では '?' を省略できる
+        let expected = pair(yaml!(["a", "b"]), yaml!("c"));
+        assert_eq!(parse_yaml_helper("{? [a, b]: c}").unwrap(), expected);
+        assert_eq!(parse_yaml_helper("{[a, b]: c}").unwrap(), expected);
+        assert_eq!(parse_yaml_helper("[? a : b]").unwrap(), yaml!([{"a": "b"}]));
+    }
+
+    #[test]
+    fn test_unexpected_indentation() {
+        match parse_yaml_helper("a: 1\n  b: 2") {
+            Err(YamlError::IndentationError { span, .. }) => {
+                assert_eq!((span.line, span.column), (2, 3));
+            }
+            other => panic!("Expected indentation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_locate_node_spans() {
+        let input = "base: &b\n  port: x\nservers:\n  - name: a\n    ports: [1, two]\n  - <<: *b\nempty:\nnext: 1\n";
+        let tokens = Lexer::new(input).tokenize_with_spans().unwrap();
+        let mut parser = Parser::with_spans(tokens).track_node_spans();
+        parser.parse().unwrap();
+
+        let locate = |path: &str| {
+            let error = YamlError::invalid_value("test");
+            let error = path.split('.').rev().fold(error, |e, key| e.in_field(key));
+            parser.locate(error).span().map(|span| (span.line, span.column))
+        };
+        assert_eq!(locate("base.port"), Some((2, 9)));
+        assert_eq!(locate("servers"), Some((4, 3)));
+        // 空の値はキーの行の末尾を指す
+        assert_eq!(locate("empty"), Some((7, 7)));
+
+        // マージされた値は最も近い祖先の位置
+        let error = parser.locate(YamlError::invalid_value("test").in_field("port").at_index(1).in_field("servers"));
+        assert_eq!(error.span().map(|span| (span.line, span.column)), Some((6, 5)));
+
+        let error = parser.locate(YamlError::invalid_value("test").at_index(1).in_field("ports").at_index(0).in_field("servers"));
+        assert_eq!(error.span().map(|span| (span.line, span.column)), Some((5, 16)));
+    }
+
+    #[test]
+    fn test_mapping_preserves_document_order() {
+        let yaml = "zeta: 1\nalpha:\n  y: 2\n  b: 3\nbase: &b {k: 1, a: 2}\nmerged:\n  z: 0\n  <<: *b\n";
+        let result = parse_yaml_helper(yaml).unwrap();
+
+        let YamlValue::Object(map) = result else { panic!("Expected object") };
+        assert_eq!(map.keys().filter_map(YamlValue::as_str).collect::<Vec<_>>(), ["zeta", "alpha", "base", "merged"]);
+        match (map.get("alpha"), map.get("merged")) {
+            (Some(YamlValue::Object(alpha)), Some(YamlValue::Object(merged))) => {
+                assert_eq!(alpha.keys().filter_map(YamlValue::as_str).collect::<Vec<_>>(), ["y", "b"]);
+                assert_eq!(merged.keys().filter_map(YamlValue::as_str).collect::<Vec<_>>(), ["z", "k", "a"]);
+            }
+            _ => panic!("Expected nested objects"),
+        }
+    }
+
+    #[test]
+    fn test_parse_borrowed_values() {
+        let yaml = "routes:\n  - path: /users\n    name: 'list users'\n    note: \"a\\tb\"\n  - path: {x: 1}\nbase: &b {k: v}\nmerged:\n  k: own\n  <<: *b\n  k: last\n";
+        let mut parser = Parser::from_lexer(Lexer::new(yaml));
+        let value = parser.parse_borrowed().unwrap();
+
+        let first = match value.get("routes") {
+            Some(YamlValueRef::Array(routes)) => &routes[0],
+            other => panic!("Expected sequence, found {:?}", other),
+        };
+        // エスケープのないスカラーとキーは入力を指す
+        assert!(matches!(first.get("path"), Some(YamlValueRef::String(Cow::Borrowed("/users")))));
+        assert!(matches!(first.get("name"), Some(YamlValueRef::String(Cow::Borrowed("list users")))));
+        assert!(matches!(first.get("note"), Some(YamlValueRef::String(Cow::Owned(s))) if s == "a\tb"));
+        let YamlValueRef::Object(entries) = first else { panic!("Expected mapping") };
+        assert!(entries.iter().all(|(key, _)| matches!(key, YamlValueRef::String(Cow::Borrowed(_)))));
+
+        // 重複したキーは最初の位置で後の値になり、マージキーは上書きしない
+        let Some(YamlValueRef::Object(merged)) = value.get("merged") else { panic!("Expected mapping") };
+        assert_eq!(merged, &vec![(YamlValueRef::String(Cow::Borrowed("k")), YamlValueRef::String(Cow::Borrowed("last")))]);
+
+        assert_eq!(value.into_owned(), parse_yaml_helper(yaml).unwrap());
+    }
+
+    #[test]
+    fn test_parse_borrowed_from_reader_owns_strings() {
+        let mut parser = Parser::from_lexer(Lexer::from_reader("a: b\n".as_bytes()));
+        let value = parser.parse_borrowed().unwrap();
+        assert!(matches!(value.get("a"), Some(YamlValueRef::String(Cow::Owned(s))) if s == "b"));
+    }
+
+    #[test]
+    fn test_parse_tagged_values() {
+        let yaml = "bucket: !Ref MyBucket\narn: !Sub\n  - 'arn:${X}'\n  - {X: !GetAtt Role.Arn}\nport: !!str 8080\nbase: &b !Env {k: v}\ncopy: *b\nempty: !Ref\n";
+        let value = parse_yaml_helper(yaml).unwrap();
+
+        let tagged = |tag: &str, value| YamlValue::Tagged(tag.to_string(), Box::new(value));
+        assert_eq!(value["bucket"], tagged("!Ref", yaml!("MyBucket")));
+        assert_eq!(value["arn"], tagged("!Sub", yaml!(["arn:${X}", {"X": (tagged("!GetAtt", yaml!("Role.Arn")))}])));
+        assert_eq!(value["port"], yaml!("8080"));
+        assert_eq!(value["copy"], tagged("!Env", yaml!({"k": "v"})));
+        assert_eq!(value["empty"], tagged("!Ref", YamlValue::Null));
+
+        let mut parser = Parser::from_lexer(Lexer::new(yaml));
+        let borrowed = parser.parse_borrowed().unwrap();
+        assert_eq!(borrowed.into_owned(), value);
+    }
+
+    #[test]
+    fn test_tag_resolvers() {
+        let yaml = "a: !Upper x\nb: !!binary aGk=\nc: !Pair [1, 2]\nd: &p !Upper y\ne: *p\nf: !Ref z\n";
+        let mut parser = Parser::from_lexer(Lexer::new(yaml))
+            .with_tag_resolver("!Upper", |value| Ok(value.as_str().unwrap_or_default().to_uppercase().into()))
+            .with_tag_resolver("!!binary", |_| Ok(YamlValue::String("hi".to_string())))
+            .with_tag_resolver("!Pair", |value| {
+                let items = value.as_array().filter(|items| items.len() == 2);
+                let items = items.ok_or_else(|| YamlError::invalid_value("Expected a pair"))?;
+                Ok(yaml!({"first": (items[0].clone()), "second": (items[1].clone())}))
+            });
+        let value = parser.parse().unwrap();
+
+        // 変換結果はアンカーにも登録される
+        assert_eq!(value, yaml!({
+            "a": "X",
+            "b": "hi",
+            "c": {"first": 1, "second": 2},
+            "d": "Y",
+            "e": "Y",
+            "f": (YamlValue::Tagged("!Ref".to_string(), Box::new(yaml!("z")))),
+        }));
+
+        let mut parser = Parser::from_lexer(Lexer::new("a:\n  - !Pair [1]"))
+            .with_tag_resolver("!Pair", |_| Err(YamlError::invalid_value("Expected a pair")));
+        match parser.parse() {
+            Err(YamlError::InvalidValue { message, span: Some(span), .. }) => {
+                assert_eq!(message, "Expected a pair");
+                assert_eq!((span.line, span.column), (2, 11));
+            }
+            other => panic!("Expected InvalidValue error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_with_schema() {
+        let yaml = "a: yes\nb: 0x1F\nc: 1:20\nd: -.Inf\ne: null\nf: 'true'\ng: [on, 0b1010]\n";
+        let parse = |schema| Parser::from_lexer(Lexer::new(yaml)).with_schema(schema).parse().unwrap();
+
+        let core = parse(Schema::Core);
+        assert_eq!(core["a"], YamlValue::String("yes".to_string()));
+        assert_eq!(core["b"], YamlValue::Integer(31));
+        assert_eq!(core["c"], YamlValue::String("1:20".to_string()));
+        assert_eq!(core["d"], YamlValue::Float(f64::NEG_INFINITY));
+
+        let yaml11 = parse(Schema::Yaml11);
+        assert_eq!(yaml11["a"], YamlValue::Boolean(true));
+        assert_eq!(yaml11["c"], YamlValue::Integer(80));
+        assert_eq!(yaml11["g"], yaml!([true, 10]));
+
+        let json = parse(Schema::Json);
+        assert_eq!(json["b"], YamlValue::String("0x1F".to_string()));
+        assert_eq!(json["e"], YamlValue::Null);
+
+        // 引用符付きスカラーはどのスキーマでも文字列
+        let failsafe = parse(Schema::Failsafe);
+        assert_eq!(failsafe["e"], YamlValue::String("null".to_string()));
+        assert_eq!(failsafe["f"], YamlValue::String("true".to_string()));
+        assert_eq!(core["f"], YamlValue::String("true".to_string()));
+    }
+}
@@ -0,0 +1,467 @@
+//! Resolution of plain scalars to types
+//!
+//! Quoted and block scalars are always strings; a [`Schema`] decides what
+//! an unquoted scalar such as `yes`, `0x1F` or `.inf` means.
+//!
+//! ```rust
+//! use yaml_parser::{parse_yaml_with_schema, Schema, YamlValue};
+//!
+//! let yaml = "enabled: yes\nmode: 0o755";
+//! let core = parse_yaml_with_schema(yaml, Schema::Core).unwrap();
+//! assert_eq!(core["enabled"], YamlValue::String("yes".to_string()));
+//! assert_eq!(core["mode"], YamlValue::Integer(0o755));
+//!
+//! let yaml11 = parse_yaml_with_schema(yaml, Schema::Yaml11).unwrap();
+//! assert_eq!(yaml11["enabled"], YamlValue::Boolean(true));
+//! ```
+
+use std::borrow::Cow;
+
+use crate::value::{YamlValue, YamlValueRef};
+
+/// How plain (unquoted) scalars are resolved
+///
+/// Empty nodes are null under every schema. Plain scalars that match none
+/// of a schema's forms are strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Schema {
+    /// Every plain scalar is a string
+    Failsafe,
+    /// JSON literals only: `null`, `true`, `false` and JSON numbers
+    Json,
+    /// The YAML 1.2 core schema: `~`, `True`, `0x1F`, `0o17`, `.inf`, `.nan`, ...
+    #[default]
+    Core,
+    /// YAML 1.1 types: `yes`/`no`/`on`/`off`, `0b1010`, `017` (octal),
+    /// sexagesimal `1:20` and `_` digit separators
+    Yaml11,
+}
+
+impl Schema {
+    /// Resolve the text of a plain scalar
+    pub fn resolve(self, text: &str) -> YamlValue {
+        self.resolve_borrowed(Cow::Borrowed(text)).into_owned()
+    }
+
+    // 文字列として解決された場合はテキストをそのまま使う
+    pub(crate) fn resolve_borrowed(self, text: Cow<'_, str>) -> YamlValueRef<'_> {
+        if text.is_empty() {
+            return YamlValueRef::Null;
+        }
+
+        let resolved = match self {
+            Schema::Failsafe => None,
+            Schema::Json => resolve_json(&text),
+            Schema::Core => resolve_core(&text),
+            Schema::Yaml11 => resolve_yaml11(&text),
+        };
+        resolved.unwrap_or(YamlValueRef::String(text))
+    }
+
+    // 標準タグ（!!str, !!int など、接頭辞を除いた名前）の付いたスカラーを解決する。
+    // テキストがその型として読めなければ None
+    pub(crate) fn resolve_tagged<'t>(self, name: &str, text: Cow<'t, str>) -> Option<YamlValueRef<'t>> {
+        if name == "str" {
+            return Some(YamlValueRef::String(text));
+        }
+
+        // 型の判定は常に core（YAML 1.1 スキーマではその形式）で行う
+        let schema = match self {
+            Schema::Yaml11 => Schema::Yaml11,
+            _ => Schema::Core,
+        };
+        match (name, schema.resolve_borrowed(text)) {
+            ("null", value @ YamlValueRef::Null)
+            | ("bool", value @ YamlValueRef::Boolean(_))
+            | ("int", value @ YamlValueRef::Integer(_))
+            | ("float", value @ YamlValueRef::Float(_)) => Some(value),
+            ("float", YamlValueRef::Integer(i)) => Some(YamlValueRef::Float(i as f64)),
+            _ => None,
+        }
+    }
+}
+
+/// Prefix of the tags defined by the YAML specification; `!!int` is short
+/// for `tag:yaml.org,2002:int`
+pub const YAML_TAG_PREFIX: &str = "tag:yaml.org,2002:";
+
+// 展開済みのタグを書き出す形式（!!int、!Ref、!<tag:example.com,2000:app>）。
+// 短縮形で書けない接尾辞（`!e!` など）は逐語的タグにする
+pub(crate) fn short_tag(tag: &str) -> Cow<'_, str> {
+    match tag.strip_prefix(YAML_TAG_PREFIX) {
+        Some(name) if is_tag_suffix(name) => Cow::Owned(format!("!!{}", name)),
+        _ if tag == "!" || tag.strip_prefix('!').is_some_and(is_tag_suffix) => Cow::Borrowed(tag),
+        _ if is_uri(tag) => Cow::Owned(format!("!<{}>", tag)),
+        // URI に使えない文字（`%22` などの展開結果）は、読み込み時に展開される
+        // 短縮形の % エスケープで書く
+        Some(name) => Cow::Owned(format!("!!{}", escape_tag_suffix(name))),
+        None => match tag.strip_prefix('!') {
+            Some(suffix) => Cow::Owned(format!("!{}", escape_tag_suffix(suffix))),
+            None => Cow::Owned(format!("!<{}>", tag)),
+        },
+    }
+}
+
+// 逐語的タグ（`!<...>`）の中身に使える URI の文字と `%` エスケープだけからなるか
+pub(crate) fn is_uri(text: &str) -> bool {
+    is_escaped_with(text, is_uri_char)
+}
+
+// 短縮形のタグの接尾辞に使えるか（URI の文字から `!` とフローの区切りを除く）
+pub(crate) fn is_tag_suffix(text: &str) -> bool {
+    is_escaped_with(text, is_tag_suffix_char)
+}
+
+fn is_tag_suffix_char(b: u8) -> bool {
+    is_uri_char(b) && !matches!(b, b'!' | b',' | b'[' | b']' | b'{' | b'}')
+}
+
+// 接尾辞に使えないバイトを % エスケープする
+fn escape_tag_suffix(suffix: &str) -> String {
+    let mut escaped = String::with_capacity(suffix.len());
+    for &b in suffix.as_bytes() {
+        if is_tag_suffix_char(b) {
+            escaped.push(b as char);
+        } else {
+            escaped.push_str(&format!("%{:02X}", b));
+        }
+    }
+    escaped
+}
+
+fn is_uri_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-#;/?:@&=+$,_.!~*'()[]".contains(&b)
+}
+
+fn is_escaped_with(text: &str, allowed: impl Fn(u8) -> bool) -> bool {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if bytes.get(i + 1..i + 3).is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) => i += 3,
+            b if allowed(b) => i += 1,
+            _ => return false,
+        }
+    }
+    !bytes.is_empty()
+}
+
+fn resolve_json(text: &str) -> Option<YamlValueRef<'static>> {
+    match text {
+        "null" => Some(YamlValueRef::Null),
+        "true" => Some(YamlValueRef::Boolean(true)),
+        "false" => Some(YamlValueRef::Boolean(false)),
+        _ => {
+            let digits = text.strip_prefix('-').unwrap_or(text);
+            let (int, rest) = split_digits(digits);
+            if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+                return None;
+            }
+            if rest.is_empty() {
+                return Some(decimal(text));
+            }
+            let rest = match rest.strip_prefix('.') {
+                Some(fraction) => split_digits(fraction).1,
+                None => rest,
+            };
+            (rest.is_empty() || is_exponent(rest, false)).then(|| float(text))
+        }
+    }
+}
+
+fn resolve_core(text: &str) -> Option<YamlValueRef<'static>> {
+    match text {
+        "null" | "Null" | "NULL" | "~" => return Some(YamlValueRef::Null),
+        "true" | "True" | "TRUE" => return Some(YamlValueRef::Boolean(true)),
+        "false" | "False" | "FALSE" => return Some(YamlValueRef::Boolean(false)),
+        ".nan" | ".NaN" | ".NAN" => return Some(YamlValueRef::Float(f64::NAN)),
+        _ => {}
+    }
+
+    if let Some(hex) = text.strip_prefix("0x") {
+        return radix(hex, 16, false);
+    }
+    if let Some(octal) = text.strip_prefix("0o") {
+        return radix(octal, 8, false);
+    }
+
+    let (negative, unsigned) = split_sign(text);
+    if matches!(unsigned, ".inf" | ".Inf" | ".INF") {
+        return Some(infinity(negative));
+    }
+
+    let (int, rest) = split_digits(unsigned);
+    if rest.is_empty() {
+        return (!int.is_empty()).then(|| decimal(text));
+    }
+
+    // [0-9]+(\.[0-9]*)? または \.[0-9]+、その後に指数
+    let rest = match rest.strip_prefix('.') {
+        Some(fraction) => {
+            let (fraction, rest) = split_digits(fraction);
+            if int.is_empty() && fraction.is_empty() {
+                return None;
+            }
+            rest
+        }
+        None if int.is_empty() => return None,
+        None => rest,
+    };
+    (rest.is_empty() || is_exponent(rest, false)).then(|| float(text))
+}
+
+fn resolve_yaml11(text: &str) -> Option<YamlValueRef<'static>> {
+    match text {
+        "null" | "Null" | "NULL" | "~" => return Some(YamlValueRef::Null),
+        "true" | "True" | "TRUE" | "yes" | "Yes" | "YES" | "on" | "On" | "ON" => {
+            return Some(YamlValueRef::Boolean(true));
+        }
+        "false" | "False" | "FALSE" | "no" | "No" | "NO" | "off" | "Off" | "OFF" => {
+            return Some(YamlValueRef::Boolean(false));
+        }
+        ".nan" | ".NaN" | ".NAN" => return Some(YamlValueRef::Float(f64::NAN)),
+        _ => {}
+    }
+
+    let (negative, unsigned) = split_sign(text);
+    if matches!(unsigned, ".inf" | ".Inf" | ".INF") {
+        return Some(infinity(negative));
+    }
+
+    let value = if let Some(binary) = unsigned.strip_prefix("0b") {
+        radix(binary, 2, true)?
+    } else if let Some(hex) = unsigned.strip_prefix("0x") {
+        radix(hex, 16, true)?
+    } else if unsigned.contains(':') {
+        sexagesimal(unsigned)?
+    } else if unsigned.len() > 1 && unsigned.starts_with('0') && !unsigned.contains(['.', 'e', 'E']) {
+        radix(&unsigned[1..], 8, true)?
+    } else {
+        return yaml11_decimal(text, unsigned);
+    };
+
+    Some(match value {
+        YamlValueRef::Integer(i) if negative => YamlValueRef::Integer(i.checked_neg()?),
+        YamlValueRef::Float(f) if negative => YamlValueRef::Float(-f),
+        value => value,
+    })
+}
+
+// YAML 1.1 の10進整数（[1-9][0-9_]* または 0）と浮動小数点数（'.' が必須、指数には符号が必須）
+fn yaml11_decimal(text: &str, unsigned: &str) -> Option<YamlValueRef<'static>> {
+    let int_end = unsigned
+        .find(|c: char| !(c.is_ascii_digit() || c == '_'))
+        .unwrap_or(unsigned.len());
+    let (int, rest) = unsigned.split_at(int_end);
+    if int.starts_with('_') {
+        return None;
+    }
+    let cleaned = text.replace('_', "");
+
+    if rest.is_empty() {
+        return (!int.is_empty()).then(|| decimal(&cleaned));
+    }
+
+    let fraction = rest.strip_prefix('.')?;
+    let fraction_end = fraction
+        .find(|c: char| !(c.is_ascii_digit() || c == '_'))
+        .unwrap_or(fraction.len());
+    let (digits, exponent) = fraction.split_at(fraction_end);
+    if int.is_empty() && digits.trim_matches('_').is_empty() {
+        return None;
+    }
+    (exponent.is_empty() || is_exponent(exponent, true)).then(|| float(&cleaned))
+}
+
+// 60進数（1:20 や 190:20:30.15）。最初の部分以外は 0-59
+fn sexagesimal(text: &str) -> Option<YamlValueRef<'static>> {
+    let mut parts = text.split(':');
+    let first = parts.next()?.replace('_', "");
+    if first.is_empty() || first.starts_with('0') || !first.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut parts: Vec<&str> = parts.collect();
+    let last = parts.pop()?;
+    let (last, fraction) = match last.split_once('.') {
+        Some((last, fraction)) => (last, Some(fraction)),
+        None => (last, None),
+    };
+
+    let mut value: i64 = first.parse().ok()?;
+    for part in parts.into_iter().chain([last]) {
+        if part.is_empty() || part.len() > 2 || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let digit: i64 = part.parse().ok()?;
+        if digit >= 60 {
+            return None;
+        }
+        value = value.checked_mul(60)?.checked_add(digit)?;
+    }
+
+    match fraction {
+        None => Some(YamlValueRef::Integer(value)),
+        Some(fraction) => {
+            let fraction = fraction.replace('_', "");
+            if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            format!("{}.{}", value, fraction).parse().ok().map(YamlValueRef::Float)
+        }
+    }
+}
+
+// 基数付き整数。separators が true なら '_' を読み飛ばす
+fn radix(digits: &str, radix: u32, separators: bool) -> Option<YamlValueRef<'static>> {
+    let digits: Cow<'_, str> = if separators {
+        Cow::Owned(digits.replace('_', ""))
+    } else {
+        Cow::Borrowed(digits)
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    i64::from_str_radix(&digits, radix).ok().map(YamlValueRef::Integer)
+}
+
+// 10進整数。i64 に収まらなければ浮動小数点数にする
+fn decimal(text: &str) -> YamlValueRef<'static> {
+    match text.parse::<i64>() {
+        Ok(i) => YamlValueRef::Integer(i),
+        Err(_) => float(text),
+    }
+}
+
+// 形式を検証済みのテキストのみを渡す
+fn float(text: &str) -> YamlValueRef<'static> {
+    YamlValueRef::Float(text.parse().expect("validated float syntax"))
+}
+
+fn infinity(negative: bool) -> YamlValueRef<'static> {
+    YamlValueRef::Float(if negative { f64::NEG_INFINITY } else { f64::INFINITY })
+}
+
+fn split_sign(text: &str) -> (bool, &str) {
+    match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    }
+}
+
+// 先頭の10進数字の並びと残り
+fn split_digits(text: &str) -> (&str, &str) {
+    let end = text.bytes().position(|b| !b.is_ascii_digit()).unwrap_or(text.len());
+    text.split_at(end)
+}
+
+// [eE][-+]?[0-9]+（sign_required なら符号が必須）
+fn is_exponent(text: &str, sign_required: bool) -> bool {
+    let Some(rest) = text.strip_prefix(['e', 'E']) else {
+        return false;
+    };
+    let digits = match rest.strip_prefix(['-', '+']) {
+        Some(digits) => digits,
+        None if sign_required => return false,
+        None => rest,
+    };
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_all(schema: Schema, inputs: &[&str]) -> Vec<YamlValue> {
+        inputs.iter().map(|text| schema.resolve(text)).collect()
+    }
+
+    fn string(s: &str) -> YamlValue {
+        YamlValue::String(s.to_string())
+    }
+
+    #[test]
+    fn test_failsafe_schema() {
+        assert_eq!(
+            resolve_all(Schema::Failsafe, &["true", "1", "null"]),
+            vec![string("true"), string("1"), string("null")]
+        );
+        assert_eq!(Schema::Failsafe.resolve(""), YamlValue::Null);
+    }
+
+    #[test]
+    fn test_json_schema() {
+        assert_eq!(
+            resolve_all(Schema::Json, &["null", "true", "-12", "0", "1.5e3", "-0.5", "2E-2"]),
+            vec![
+                YamlValue::Null,
+                YamlValue::Boolean(true),
+                YamlValue::Integer(-12),
+                YamlValue::Integer(0),
+                YamlValue::Float(1500.0),
+                YamlValue::Float(-0.5),
+                YamlValue::Float(0.02),
+            ]
+        );
+        for text in ["Null", "True", "~", "+1", "01", ".5", "0x1F", ".inf", "inf", "NaN"] {
+            assert_eq!(Schema::Json.resolve(text), string(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_core_schema() {
+        assert_eq!(
+            resolve_all(Schema::Core, &["~", "NULL", "True", "0x1F", "0o17", "+12", "-007", "1.", ".5", "1e3", "-.Inf", ".inf"]),
+            vec![
+                YamlValue::Null,
+                YamlValue::Null,
+                YamlValue::Boolean(true),
+                YamlValue::Integer(31),
+                YamlValue::Integer(15),
+                YamlValue::Integer(12),
+                YamlValue::Integer(-7),
+                YamlValue::Float(1.0),
+                YamlValue::Float(0.5),
+                YamlValue::Float(1000.0),
+                YamlValue::Float(f64::NEG_INFINITY),
+                YamlValue::Float(f64::INFINITY),
+            ]
+        );
+        assert!(matches!(Schema::Core.resolve(".NaN"), YamlValue::Float(f) if f.is_nan()));
+        // i64 に収まらない10進整数は浮動小数点数
+        assert_eq!(Schema::Core.resolve("99999999999999999999"), YamlValue::Float(1e20));
+
+        for text in ["yes", "inf", "NaN", "infinity", "0x", "0xG", "-0x1", "0b1", "1_000", ".", "1e", "1:20", "TrUe"] {
+            assert_eq!(Schema::Core.resolve(text), string(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_yaml11_schema() {
+        assert_eq!(
+            resolve_all(Schema::Yaml11, &["yes", "Off", "ON", "0b1010", "-0b11", "017", "0x_1F", "1_000", "1:20", "-1:30:00", "190:20:30.15", "1.5e+3", ".5", "-.inf", "0"]),
+            vec![
+                YamlValue::Boolean(true),
+                YamlValue::Boolean(false),
+                YamlValue::Boolean(true),
+                YamlValue::Integer(10),
+                YamlValue::Integer(-3),
+                YamlValue::Integer(15),
+                YamlValue::Integer(31),
+                YamlValue::Integer(1000),
+                YamlValue::Integer(80),
+                YamlValue::Integer(-5400),
+                YamlValue::Float(685230.15),
+                YamlValue::Float(1500.0),
+                YamlValue::Float(0.5),
+                YamlValue::Float(f64::NEG_INFINITY),
+                YamlValue::Integer(0),
+            ]
+        );
+
+        for text in ["y", "n", "0o17", "09", "1e3", "1:60", "01:20", "_1", "1.5e3", "."] {
+            assert_eq!(Schema::Yaml11.resolve(text), string(text), "{}", text);
+        }
+    }
+}
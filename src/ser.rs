@@ -0,0 +1,494 @@
+//! Serde support for writing YAML (enabled with the `serde` feature)
+//!
+//! [`Serializer`] turns any `serde::Serialize` type into a [`YamlValue`];
+//! [`to_string`] then writes it with the default [`Emitter`].
+//!
+//! ```rust
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! let server = Server { host: "localhost".to_string(), port: 8080 };
+//! assert_eq!(yaml_parser::to_string(&server).unwrap(), "host: localhost\nport: 8080\n");
+//! ```
+//!
+//! Enums use the same externally tagged form that [`from_str`](crate::from_str)
+//! reads: a unit variant is a plain string, other variants a single-entry
+//! mapping from the variant name to its content.
+
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use crate::emitter::Emitter;
+use crate::error::{Result, YamlError};
+use crate::mapping::{key_text, Mapping};
+use crate::value::YamlValue;
+
+impl ser::Error for YamlError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        YamlError::invalid_value(msg.to_string())
+    }
+}
+
+/// Serialize any `Serialize` type into a YamlValue
+///
+/// # Arguments
+///
+/// * `value` - The value to convert
+///
+/// # Returns
+///
+/// Returns a `Result<YamlValue>`; fails for integers outside the `i64` range.
+/// Map keys of any type become `YamlValue` keys.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<YamlValue> {
+    value.serialize(Serializer)
+}
+
+/// Serialize any `Serialize` type to YAML text using the default emitter options
+///
+/// # Arguments
+///
+/// * `value` - The value to serialize
+///
+/// # Returns
+///
+/// Returns the YAML text, terminated by a newline
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+///
+/// let ports = BTreeMap::from([("http", 80), ("https", 443)]);
+/// assert_eq!(yaml_parser::to_string(&ports).unwrap(), "http: 80\nhttps: 443\n");
+/// ```
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    Ok(Emitter::default().emit(&to_value(value)?))
+}
+
+/// A `serde::Serializer` whose output is a [`YamlValue`]
+pub struct Serializer;
+
+// バリアント名をキーとする1要素のマッピング
+fn tagged(variant: &'static str, value: YamlValue) -> YamlValue {
+    let mut map = Mapping::new();
+    map.insert(variant.to_string(), value);
+    YamlValue::Object(map)
+}
+
+fn integer<T: TryInto<i64> + fmt::Display + Copy>(value: T) -> Result<YamlValue> {
+    value
+        .try_into()
+        .map(YamlValue::Integer)
+        .map_err(|_| YamlError::invalid_value(format!("Integer {} is out of range for a YAML integer", value)))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = YamlValue;
+    type Error = YamlError;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVec;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<YamlValue> {
+        Ok(YamlValue::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<YamlValue> {
+        integer(v)
+    }
+
+    // f32 は表示形式を経由して、0.1 が 0.10000000149 にならないようにする
+    fn serialize_f32(self, v: f32) -> Result<YamlValue> {
+        Ok(YamlValue::Float(v.to_string().parse().unwrap_or(v as f64)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<YamlValue> {
+        Ok(YamlValue::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<YamlValue> {
+        Ok(YamlValue::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<YamlValue> {
+        Ok(YamlValue::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<YamlValue> {
+        Ok(YamlValue::Array(v.iter().map(|b| YamlValue::Integer(*b as i64)).collect()))
+    }
+
+    fn serialize_none(self) -> Result<YamlValue> {
+        Ok(YamlValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<YamlValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<YamlValue> {
+        Ok(YamlValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<YamlValue> {
+        Ok(YamlValue::Null)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<YamlValue> {
+        Ok(YamlValue::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<YamlValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<YamlValue> {
+        Ok(tagged(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec> {
+        Ok(SerializeVec { items: Vec::with_capacity(len.unwrap_or(0)), variant: None })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec> {
+        Ok(SerializeVec { items: Vec::with_capacity(len), variant: Some(variant) })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap> {
+        Ok(SerializeMap { map: Mapping::with_capacity(len.unwrap_or(0)), key: None, variant: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap> {
+        Ok(SerializeMap { map: Mapping::with_capacity(len), key: None, variant: Some(variant) })
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeVec {
+    items: Vec<YamlValue>,
+    variant: Option<&'static str>, // タプルバリアントの場合はバリアント名
+}
+
+impl SerializeVec {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let index = self.items.len();
+        self.items.push(value.serialize(Serializer).map_err(|e| e.at_index(index))?);
+        Ok(())
+    }
+
+    fn finish(self) -> YamlValue {
+        let array = YamlValue::Array(self.items);
+        match self.variant {
+            Some(variant) => tagged(variant, array),
+            None => array,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = YamlValue;
+    type Error = YamlError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<YamlValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = YamlValue;
+    type Error = YamlError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<YamlValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = YamlValue;
+    type Error = YamlError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<YamlValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVec {
+    type Ok = YamlValue;
+    type Error = YamlError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<YamlValue> {
+        Ok(self.finish())
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeMap {
+    map: Mapping,
+    key: Option<YamlValue>,        // serialize_value を待っているキー
+    variant: Option<&'static str>, // 構造体バリアントの場合はバリアント名
+}
+
+impl SerializeMap {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: YamlValue, value: &T) -> Result<()> {
+        let value = value.serialize(Serializer).map_err(|e| e.in_field(&key_text(&key)))?;
+        self.map.insert(key, value);
+        Ok(())
+    }
+
+    fn finish(self) -> YamlValue {
+        let map = YamlValue::Object(self.map);
+        match self.variant {
+            Some(variant) => tagged(variant, map),
+            None => map,
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = YamlValue;
+    type Error = YamlError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| YamlError::invalid_value("serialize_value called before serialize_key"))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<YamlValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = YamlValue;
+    type Error = YamlError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.insert(key.into(), value)
+    }
+
+    fn end(self) -> Result<YamlValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = YamlValue;
+    type Error = YamlError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.insert(key.into(), value)
+    }
+
+    fn end(self) -> Result<YamlValue> {
+        Ok(self.finish())
+    }
+}
+
+impl Serialize for YamlValue {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            YamlValue::String(s) => serializer.serialize_str(s),
+            YamlValue::Integer(i) => serializer.serialize_i64(*i),
+            YamlValue::Float(f) => serializer.serialize_f64(*f),
+            YamlValue::Boolean(b) => serializer.serialize_bool(*b),
+            YamlValue::Array(items) => serializer.collect_seq(items),
+            YamlValue::Object(map) => map.serialize(serializer),
+            YamlValue::Null => serializer.serialize_unit(),
+            // タグは他の形式で表せないため、値だけを書く
+            YamlValue::Tagged(_, value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Mapping {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Service {
+        name: String,
+        replicas: u32,
+        ratio: f32,
+        ports: Vec<u16>,
+        env: BTreeMap<String, String>,
+        owner: Option<String>,
+        shape: Shape,
+        point: (i8, i8),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Line(i32, i32),
+        Rect { w: u32, h: u32 },
+    }
+
+    fn service() -> Service {
+        Service {
+            name: "api".to_string(),
+            replicas: 3,
+            ratio: 0.1,
+            ports: vec![80, 443],
+            env: BTreeMap::from([("MODE".to_string(), "prod".to_string())]),
+            owner: None,
+            shape: Shape::Rect { w: 2, h: 1 },
+            point: (1, -1),
+        }
+    }
+
+    #[test]
+    fn test_serialize_struct_to_string() {
+        assert_eq!(
+            to_string(&service()).unwrap(),
+            "name: api\nreplicas: 3\nratio: 0.1\nports:\n  - 80\n  - 443\nenv:\n  MODE: prod\nowner: null\nshape:\n  Rect:\n    w: 2\n    h: 1\npoint:\n  - 1\n  - -1\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_with_deserializer() {
+        let text = to_string(&service()).unwrap();
+        let restored: Service = crate::from_str(&text).unwrap();
+        assert_eq!(restored, service());
+
+        let shapes = vec![Shape::Empty, Shape::Circle(1.5), Shape::Line(1, 2)];
+        let restored: Vec<Shape> = crate::from_str(&to_string(&shapes).unwrap()).unwrap();
+        assert_eq!(restored, shapes);
+    }
+
+    #[test]
+    fn test_serialize_map_keys() {
+        // キーは数値やシーケンスのまま書く
+        let map = HashMap::from([(7u8, true)]);
+        assert_eq!(to_value(&map).unwrap(), YamlValue::Object(Mapping::from([(7, true.into())])));
+
+        let map = BTreeMap::from([(vec![1, 2], "pair"), (vec![3], "one")]);
+        let text = to_string(&map).unwrap();
+        assert_eq!(text, "? [1, 2]\n: pair\n? [3]\n: one\n");
+        assert_eq!(crate::from_str::<BTreeMap<Vec<i32>, String>>(&text).unwrap(), BTreeMap::from([
+            (vec![1, 2], "pair".to_string()),
+            (vec![3], "one".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn test_serialize_out_of_range() {
+        let error = to_value(&BTreeMap::from([("big", u64::MAX)])).unwrap_err();
+        assert_eq!(error.path(), Some("big"));
+    }
+
+    #[test]
+    fn test_yaml_value_serialize() {
+        let value = crate::parse_yaml("a: [1, x, ~]\nb: {c: 1.5}").unwrap();
+        assert_eq!(to_value(&value).unwrap(), value);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::mapping::Mapping;
+use crate::value::YamlValue;
+use std::collections::HashMap;
+
+/// Trait for types that can be serialized to YAML
+pub trait YamlSerialize {
+    /// Serialize to a YamlValue
+    fn to_yaml(&self) -> YamlValue;
+}
+
+// Implement YamlSerialize for primitive types
+impl YamlSerialize for String {
+    fn to_yaml(&self) -> YamlValue {
+        YamlValue::String(self.clone())
+    }
+}
+
+impl YamlSerialize for i64 {
+    fn to_yaml(&self) -> YamlValue {
+        YamlValue::Integer(*self)
+    }
+}
+
+impl YamlSerialize for i32 {
+    fn to_yaml(&self) -> YamlValue {
+        YamlValue::Integer(*self as i64)
+    }
+}
+
+impl YamlSerialize for f64 {
+    fn to_yaml(&self) -> YamlValue {
+        YamlValue::Float(*self)
+    }
+}
+
+impl YamlSerialize for bool {
+    fn to_yaml(&self) -> YamlValue {
+        YamlValue::Boolean(*self)
+    }
+}
+
+impl<T: YamlSerialize> YamlSerialize for Vec<T> {
+    fn to_yaml(&self) -> YamlValue {
+        YamlValue::Array(self.iter().map(T::to_yaml).collect())
+    }
+}
+
+impl<T: YamlSerialize> YamlSerialize for Option<T> {
+    fn to_yaml(&self) -> YamlValue {
+        match self {
+            Some(value) => value.to_yaml(),
+            None => YamlValue::Null,
+        }
+    }
+}
+
+impl YamlSerialize for Mapping {
+    fn to_yaml(&self) -> YamlValue {
+        YamlValue::Object(self.clone())
+    }
+}
+
+// キーの順序は Mapping への変換でソートされる
+impl YamlSerialize for HashMap<String, YamlValue> {
+    fn to_yaml(&self) -> YamlValue {
+        YamlValue::Object(self.clone().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::YamlDeserialize;
+
+    #[test]
+    fn test_primitive_serialization() {
+        assert_eq!("hello".to_string().to_yaml(), YamlValue::String("hello".to_string()));
+        assert_eq!(42i64.to_yaml(), YamlValue::Integer(42));
+        assert_eq!(7i32.to_yaml(), YamlValue::Integer(7));
+        assert_eq!(1.5f64.to_yaml(), YamlValue::Float(1.5));
+        assert_eq!(true.to_yaml(), YamlValue::Boolean(true));
+    }
+
+    #[test]
+    fn test_array_serialization() {
+        let value = vec![1i64, 2, 3].to_yaml();
+        assert_eq!(value, YamlValue::Array(vec![
+            YamlValue::Integer(1),
+            YamlValue::Integer(2),
+            YamlValue::Integer(3),
+        ]));
+    }
+
+    #[test]
+    fn test_option_serialization() {
+        assert_eq!(None::<String>.to_yaml(), YamlValue::Null);
+        assert_eq!(Some("test".to_string()).to_yaml(), YamlValue::String("test".to_string()));
+    }
+
+    #[test]
+    fn test_serialize_deserialize_round_trip() {
+        let original = vec![Some(1.5f64), None, Some(-2.0)];
+        let restored: Vec<Option<f64>> = YamlDeserialize::from_yaml(&original.to_yaml()).unwrap();
+        assert_eq!(restored, original);
+    }
+}
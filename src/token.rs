@@ -1,11 +1,16 @@
-use crate::value::YamlValue;
+use std::borrow::Cow;
+
+use crate::value::YamlValueRef;
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Token {
+pub enum Token<'a> {
     // 基本的なトークン
-    Key(String),
+    Key(Cow<'a, str>),       // プレーンなキー（型はパーサーがスキーマに従って決める）
+    QuotedKey(Cow<'a, str>), // 引用符付きのキー（常に文字列）
+    ExplicitKey,             // ? （複雑なキー）
     Colon,
-    Value(YamlValue),
+    Value(YamlValueRef<'a>), // 引用符付き・ブロックスカラー、空の値
+    Plain(Cow<'a, str>),     // プレーンスカラー（型はパーサーがスキーマに従って決める）
     
     // 構造トークン
     ListItem,           // -
@@ -16,14 +21,42 @@ pub enum Token {
     Dedent(usize),      // インデント減少
     Newline,
     
+    // ノードプロパティ
+    Anchor(String),     // &name
+    Alias(String),      // *name
+    Tag(String),        // !tag, !!str, !e!tag, !<verbatim>（ハンドルは展開しない）
+
+    // フローコレクション
+    FlowStart(char),    // [ または {
+    FlowEnd(char),      // ] または }
+    FlowSeparator,      // ,
+
+    // ドキュメント境界
+    Directive(String),  // %TAG ! tag:example.com,2000: （'%' を除いた行）
+    DocumentStart,      // ---
+    DocumentEnd,        // ...
+
     // 特殊
     Comment(String),
     Eof,
-    
-    // 将来の拡張用
-    FlowStart,          // [, {
-    FlowEnd,            // ], }
-    FlowSeparator,      // ,
+}
+
+/// Position of a token in the source text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, counted in characters
+    pub column: usize,
+    /// Byte offset from the start of the input
+    pub offset: usize,
+}
+
+/// A token together with the position where it starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken<'a> {
+    pub token: Token<'a>,
+    pub span: Span,
 }
 
 #[cfg(test)]
@@ -32,15 +65,16 @@ mod tests {
 
     #[test]
     fn test_token_equality() {
-        assert_eq!(Token::Key("test".to_string()), Token::Key("test".to_string()));
+        assert_eq!(Token::Key("test".into()), Token::Key("test".into()));
         assert_eq!(Token::Colon, Token::Colon);
         assert_eq!(Token::Indent(2), Token::Indent(2));
         assert_ne!(Token::Indent(2), Token::Indent(4));
+        assert_ne!(Token::FlowStart('['), Token::FlowStart('{'));
     }
 
     #[test]
     fn test_token_clone() {
-        let token = Token::Key("test".to_string());
+        let token = Token::Key("test".into());
         let cloned = token.clone();
         assert_eq!(token, cloned);
     }
@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+
+use crate::mapping::Mapping;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum YamlValue {
@@ -7,8 +9,361 @@ pub enum YamlValue {
     Float(f64),
     Boolean(bool),
     Array(Vec<YamlValue>),
-    Object(HashMap<String, YamlValue>),
+    Object(Mapping),
     Null,
+    /// A node with a tag that no resolver handled, such as `!Ref bucket`
+    Tagged(String, Box<YamlValue>),
+}
+
+impl YamlValue {
+    /// The string contents, if this is a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            YamlValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            YamlValue::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// The numeric value of a float or integer
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            YamlValue::Float(f) => Some(*f),
+            YamlValue::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            YamlValue::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<YamlValue>> {
+        match self {
+            YamlValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Mapping> {
+        match self {
+            YamlValue::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, YamlValue::Null)
+    }
+
+    /// The tag and the tagged node, if this is a tagged value
+    pub fn as_tagged(&self) -> Option<(&str, &YamlValue)> {
+        match self {
+            YamlValue::Tagged(tag, value) => Some((tag, value)),
+            _ => None,
+        }
+    }
+
+    pub fn as_str_mut(&mut self) -> Option<&mut String> {
+        match self {
+            YamlValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64_mut(&mut self) -> Option<&mut i64> {
+        match self {
+            YamlValue::Integer(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to a float; integers are not converted
+    pub fn as_f64_mut(&mut self) -> Option<&mut f64> {
+        match self {
+            YamlValue::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            YamlValue::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<YamlValue>> {
+        match self {
+            YamlValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Mapping> {
+        match self {
+            YamlValue::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Move the value out, leaving `Null` in its place
+    pub fn take(&mut self) -> YamlValue {
+        std::mem::replace(self, YamlValue::Null)
+    }
+}
+
+impl From<&str> for YamlValue {
+    fn from(value: &str) -> Self {
+        YamlValue::String(value.to_string())
+    }
+}
+
+impl From<String> for YamlValue {
+    fn from(value: String) -> Self {
+        YamlValue::String(value)
+    }
+}
+
+impl From<i64> for YamlValue {
+    fn from(value: i64) -> Self {
+        YamlValue::Integer(value)
+    }
+}
+
+impl From<i32> for YamlValue {
+    fn from(value: i32) -> Self {
+        YamlValue::Integer(value.into())
+    }
+}
+
+impl From<f64> for YamlValue {
+    fn from(value: f64) -> Self {
+        YamlValue::Float(value)
+    }
+}
+
+impl From<bool> for YamlValue {
+    fn from(value: bool) -> Self {
+        YamlValue::Boolean(value)
+    }
+}
+
+impl From<Mapping> for YamlValue {
+    fn from(value: Mapping) -> Self {
+        YamlValue::Object(value)
+    }
+}
+
+impl<T: Into<YamlValue>> From<Vec<T>> for YamlValue {
+    fn from(value: Vec<T>) -> Self {
+        YamlValue::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+/// `None` becomes `Null`
+impl<T: Into<YamlValue>> From<Option<T>> for YamlValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(YamlValue::Null, Into::into)
+    }
+}
+
+/// Collect into a sequence
+impl<T: Into<YamlValue>> FromIterator<T> for YamlValue {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        YamlValue::Array(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Collect key-value pairs into a mapping, in iteration order
+impl<K: Into<YamlValue>, V: Into<YamlValue>> FromIterator<(K, V)> for YamlValue {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        YamlValue::Object(iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect::<Mapping>())
+    }
+}
+
+/// A parsed value that borrows its strings from the input
+///
+/// Produced by [`parse_yaml_borrowed`](crate::parse_yaml_borrowed). Plain and
+/// quoted scalars without escape sequences point into the source text; only
+/// scalars that had to be rewritten (escapes, block scalars) own their text.
+/// Mapping entries are kept in document order with duplicate keys resolved;
+/// keys can be any value, as in [`Mapping`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum YamlValueRef<'a> {
+    String(Cow<'a, str>),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<YamlValueRef<'a>>),
+    Object(Vec<(YamlValueRef<'a>, YamlValueRef<'a>)>),
+    Null,
+    Tagged(String, Box<YamlValueRef<'a>>),
+}
+
+impl<'a> YamlValueRef<'a> {
+    /// Look up the string `key` in a mapping; `None` for other values
+    ///
+    /// Entries are searched in order, so this is linear in the mapping size.
+    pub fn get(&self, key: &str) -> Option<&YamlValueRef<'a>> {
+        match self {
+            YamlValueRef::Object(entries) => entries
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// The string contents, if this is a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            YamlValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Copy any borrowed strings, producing an owned [`YamlValue`]
+    pub fn into_owned(self) -> YamlValue {
+        match self {
+            YamlValueRef::String(s) => YamlValue::String(s.into_owned()),
+            YamlValueRef::Integer(i) => YamlValue::Integer(i),
+            YamlValueRef::Float(f) => YamlValue::Float(f),
+            YamlValueRef::Boolean(b) => YamlValue::Boolean(b),
+            YamlValueRef::Array(items) => {
+                YamlValue::Array(items.into_iter().map(YamlValueRef::into_owned).collect())
+            }
+            YamlValueRef::Object(entries) => YamlValue::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect::<Mapping>(),
+            ),
+            YamlValueRef::Null => YamlValue::Null,
+            YamlValueRef::Tagged(tag, value) => YamlValue::Tagged(tag, Box::new(value.into_owned())),
+        }
+    }
+}
+
+impl<'a> From<&'a YamlValue> for YamlValueRef<'a> {
+    fn from(value: &'a YamlValue) -> Self {
+        match value {
+            YamlValue::String(s) => YamlValueRef::String(Cow::Borrowed(s)),
+            YamlValue::Integer(i) => YamlValueRef::Integer(*i),
+            YamlValue::Float(f) => YamlValueRef::Float(*f),
+            YamlValue::Boolean(b) => YamlValueRef::Boolean(*b),
+            YamlValue::Array(items) => YamlValueRef::Array(items.iter().map(YamlValueRef::from).collect()),
+            YamlValue::Object(map) => YamlValueRef::Object(
+                map.iter()
+                    .map(|(key, value)| (YamlValueRef::from(key), YamlValueRef::from(value)))
+                    .collect(),
+            ),
+            YamlValue::Null => YamlValueRef::Null,
+            YamlValue::Tagged(tag, value) => YamlValueRef::Tagged(tag.clone(), Box::new(YamlValueRef::from(&**value))),
+        }
+    }
+}
+
+/// Take ownership of the strings of a [`YamlValue`]
+impl From<YamlValue> for YamlValueRef<'_> {
+    fn from(value: YamlValue) -> Self {
+        match value {
+            YamlValue::String(s) => YamlValueRef::String(Cow::Owned(s)),
+            YamlValue::Integer(i) => YamlValueRef::Integer(i),
+            YamlValue::Float(f) => YamlValueRef::Float(f),
+            YamlValue::Boolean(b) => YamlValueRef::Boolean(b),
+            YamlValue::Array(items) => YamlValueRef::Array(items.into_iter().map(YamlValueRef::from).collect()),
+            YamlValue::Object(map) => YamlValueRef::Object(
+                map.into_iter()
+                    .map(|(key, value)| (YamlValueRef::from(key), YamlValueRef::from(value)))
+                    .collect(),
+            ),
+            YamlValue::Null => YamlValueRef::Null,
+            YamlValue::Tagged(tag, value) => YamlValueRef::Tagged(tag, Box::new(YamlValueRef::from(*value))),
+        }
+    }
+}
+
+/// Build a [`YamlValue`] from a JSON-like literal
+///
+/// `null`, `[...]` and `{...}` build the corresponding values; anything else
+/// is a Rust expression converted with `YamlValue::from`. Keys are converted
+/// the same way, so `1: "a"` has an integer key and `[1, 2]: "b"` a sequence key.
+///
+/// ```rust
+/// use yaml_parser::{parse_yaml, yaml};
+///
+/// let port = 443;
+/// let value = yaml!({
+///     "name": "x",
+///     "ports": [80, port],
+///     "tls": null,
+///     ("env-".to_string() + "prod"): { "replicas": 3, "debug": false },
+/// });
+/// assert_eq!(value, parse_yaml("name: x\nports: [80, 443]\ntls:\nenv-prod: {replicas: 3, debug: false}").unwrap());
+/// ```
+#[macro_export]
+macro_rules! yaml {
+    // 内部規則: シーケンスの要素を1つずつ変換して集める
+    (@array [$($elems:expr,)*]) => {
+        ::std::vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] null $(, $($rest:tt)*)?) => {
+        $crate::yaml!(@array [$($elems,)* $crate::YamlValue::Null,] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] [$($inner:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::yaml!(@array [$($elems,)* $crate::yaml!([$($inner)*]),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] {$($inner:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::yaml!(@array [$($elems,)* $crate::yaml!({$($inner)*}),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] $value:expr $(, $($rest:tt)*)?) => {
+        $crate::yaml!(@array [$($elems,)* $crate::YamlValue::from($value),] $($($rest)*)?)
+    };
+
+    // 内部規則: マッピングのエントリを1つずつ追加する
+    (@object $object:ident) => {};
+    (@object $object:ident $key:tt : null $(, $($rest:tt)*)?) => {
+        $object.insert($crate::yaml!($key), $crate::YamlValue::Null);
+        $crate::yaml!(@object $object $($($rest)*)?);
+    };
+    (@object $object:ident $key:tt : [$($inner:tt)*] $(, $($rest:tt)*)?) => {
+        $object.insert($crate::yaml!($key), $crate::yaml!([$($inner)*]));
+        $crate::yaml!(@object $object $($($rest)*)?);
+    };
+    (@object $object:ident $key:tt : {$($inner:tt)*} $(, $($rest:tt)*)?) => {
+        $object.insert($crate::yaml!($key), $crate::yaml!({$($inner)*}));
+        $crate::yaml!(@object $object $($($rest)*)?);
+    };
+    (@object $object:ident $key:tt : $value:expr $(, $($rest:tt)*)?) => {
+        $object.insert($crate::yaml!($key), $crate::YamlValue::from($value));
+        $crate::yaml!(@object $object $($($rest)*)?);
+    };
+
+    (null) => {
+        $crate::YamlValue::Null
+    };
+    ([$($inner:tt)*]) => {
+        $crate::YamlValue::Array($crate::yaml!(@array [] $($inner)*))
+    };
+    ({$($inner:tt)*}) => {{
+        #[allow(unused_mut)]
+        let mut object = $crate::Mapping::new();
+        $crate::yaml!(@object object $($inner)*);
+        $crate::YamlValue::Object(object)
+    }};
+    ($other:expr) => {
+        $crate::YamlValue::from($other)
+    };
 }
 
 #[cfg(test)]
@@ -29,4 +384,104 @@ mod tests {
         let cloned = value.clone();
         assert_eq!(value, cloned);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_yaml_value_ref_into_owned() {
+        let value = YamlValueRef::Object(vec![
+            (YamlValueRef::String(Cow::Borrowed("name")), YamlValueRef::String(Cow::Borrowed("app"))),
+            (YamlValueRef::String(Cow::Owned("ports".to_string())), YamlValueRef::Array(vec![YamlValueRef::Integer(80)])),
+            (YamlValueRef::Integer(200), YamlValueRef::Null),
+        ]);
+        assert_eq!(value.get("name").and_then(YamlValueRef::as_str), Some("app"));
+        assert_eq!(value.get("missing"), None);
+
+        let owned = value.clone().into_owned();
+        let YamlValue::Object(map) = &owned else { panic!("expected mapping") };
+        assert_eq!(map.keys().filter_map(YamlValue::as_str).collect::<Vec<_>>(), vec!["name", "ports"]);
+        assert_eq!(map.get(&200), Some(&YamlValue::Null));
+        assert_eq!(map.get("ports"), Some(&YamlValue::Array(vec![YamlValue::Integer(80)])));
+        assert_eq!(YamlValueRef::from(&owned), value);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let mut value: YamlValue = vec![("name", YamlValue::from("app")), ("port", 8080.into())]
+            .into_iter()
+            .collect();
+        assert_eq!(value.as_object().map(Mapping::len), Some(2));
+        assert_eq!(value["name"].as_str(), Some("app"));
+        assert_eq!(value["port"].as_i64(), Some(8080));
+        assert_eq!(value["port"].as_f64(), Some(8080.0));
+        assert_eq!(value["port"].as_str(), None);
+        assert!(value["missing"].is_null());
+
+        *value.get_mut("port").and_then(YamlValue::as_i64_mut).unwrap() += 1;
+        value.get_mut("name").and_then(YamlValue::as_str_mut).unwrap().push_str("-v2");
+        value.as_object_mut().unwrap().insert("debug".to_string(), true.into());
+        assert_eq!(value["port"], YamlValue::Integer(8081));
+        assert_eq!(value["name"].as_str(), Some("app-v2"));
+        assert_eq!(value["debug"].as_bool(), Some(true));
+
+        let name = value.get_mut("name").unwrap().take();
+        assert_eq!(name, YamlValue::from("app-v2"));
+        assert!(value["name"].is_null());
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(YamlValue::from(vec![1, 2]), YamlValue::Array(vec![YamlValue::Integer(1), YamlValue::Integer(2)]));
+        assert_eq!(YamlValue::from(None::<bool>), YamlValue::Null);
+        assert_eq!(YamlValue::from(Some(2.5)).as_f64(), Some(2.5));
+
+        let mut items: YamlValue = ["a", "b"].into_iter().collect();
+        items.as_array_mut().unwrap().push("c".into());
+        assert_eq!(items.as_array().map(Vec::len), Some(3));
+        assert_eq!(items[2].as_str(), Some("c"));
+    }
+
+    #[test]
+    fn test_yaml_macro() {
+        let name = String::from("api");
+        let extra = YamlValue::from(vec![true]);
+        let value = yaml!({
+            "name": name,
+            "ports": [80, 400 + 43, -1],
+            "tls": null,
+            "nested": [{"a": [[], {}]}, null, extra],
+            (1 + 1): 2.5,
+        });
+
+        let mut expected = Mapping::new();
+        expected.insert("name".to_string(), YamlValue::String("api".to_string()));
+        expected.insert("ports".to_string(), YamlValue::Array(vec![
+            YamlValue::Integer(80),
+            YamlValue::Integer(443),
+            YamlValue::Integer(-1),
+        ]));
+        expected.insert("tls".to_string(), YamlValue::Null);
+        let mut inner = Mapping::new();
+        inner.insert("a".to_string(), YamlValue::Array(vec![
+            YamlValue::Array(Vec::new()),
+            YamlValue::Object(Mapping::new()),
+        ]));
+        expected.insert("nested".to_string(), YamlValue::Array(vec![
+            YamlValue::Object(inner),
+            YamlValue::Null,
+            YamlValue::Array(vec![YamlValue::Boolean(true)]),
+        ]));
+        expected.insert(2, YamlValue::Float(2.5));
+
+        assert_eq!(value, YamlValue::Object(expected));
+        assert_eq!(yaml!(null), YamlValue::Null);
+        assert_eq!(yaml!("x"), YamlValue::String("x".to_string()));
+        assert_eq!(yaml!([]), YamlValue::Array(Vec::new()));
+
+        // キーも値と同じように変換する
+        let keys = yaml!({1: "a", null: "b", [1, 2]: "c", ("api".to_string() + "-v2"): "d"});
+        let YamlValue::Object(keys) = keys else { panic!("Expected object") };
+        assert_eq!(keys.get(&1), Some(&YamlValue::from("a")));
+        assert_eq!(keys.get(&YamlValue::Null), Some(&YamlValue::from("b")));
+        assert_eq!(keys.get(&YamlValue::from(vec![1, 2])), Some(&YamlValue::from("c")));
+        assert_eq!(keys.get("api-v2"), Some(&YamlValue::from("d")));
+    }
+}
@@ -0,0 +1,166 @@
+//! Derive macro for the `yaml-parser` crate.
+//!
+//! Enable the `derive` feature of `yaml-parser` and use
+//! `#[derive(YamlDeserialize)]` instead of writing `from_yaml` by hand:
+//!
+//! ```rust,ignore
+//! use yaml_parser::{parse_yaml_to, YamlDeserialize};
+//!
+//! #[derive(YamlDeserialize)]
+//! struct Server {
+//!     host: String,
+//!     #[yaml(rename = "listen-port")]
+//!     port: i64,
+//!     #[yaml(default)]
+//!     workers: i64,
+//!     tls: Option<bool>,
+//!     #[yaml(skip)]
+//!     connections: Vec<String>,
+//! }
+//! ```
+//!
+//! Field attributes:
+//!
+//! - `#[yaml(rename = "name")]` reads the field from a different key
+//! - `#[yaml(default)]` uses `Default::default()` when the key is missing or null
+//! - `#[yaml(skip)]` never reads the field and fills it with `Default::default()`
+//!
+//! `Option<T>` fields are optional; every other field is required.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Field, Fields, GenericParam, LitStr, Type};
+
+/// Derive `yaml_parser::YamlDeserialize` for a struct with named fields.
+#[proc_macro_derive(YamlDeserialize, attributes(yaml))]
+pub fn derive_yaml_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// フィールドごとの `#[yaml(...)]` 属性
+#[derive(Default)]
+struct FieldOptions {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().cloned().collect::<Vec<_>>(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "YamlDeserialize can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "YamlDeserialize can only be derived for structs",
+            ));
+        }
+    };
+
+    // 型パラメータには YamlDeserialize の境界を追加する
+    for param in &mut input.generics.params {
+        if let GenericParam::Type(ty) = param {
+            ty.bounds.push(parse_quote!(::yaml_parser::YamlDeserialize));
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let initializers = fields
+        .iter()
+        .map(field_initializer)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let body = if matches!(&input.data, Data::Struct(data) if matches!(data.fields, Fields::Unit)) {
+        quote! { Self }
+    } else {
+        quote! { Self { #(#initializers,)* } }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::yaml_parser::YamlDeserialize for #name #ty_generics #where_clause {
+            fn from_yaml(value: &::yaml_parser::YamlValue) -> ::yaml_parser::Result<Self> {
+                ::std::result::Result::Ok(#body)
+            }
+        }
+    })
+}
+
+fn field_initializer(field: &Field) -> syn::Result<TokenStream2> {
+    let options = field_options(field)?;
+    let ident = field.ident.as_ref().expect("named field");
+
+    if options.skip {
+        return Ok(quote! { #ident: ::std::default::Default::default() });
+    }
+
+    let key = options.rename.unwrap_or_else(|| ident.to_string());
+
+    // 欠けたキーと null は None（default なら Default）。Option<T> として取り出すと
+    // null は Some(None) になるので、flatten で欠けたキーと同じ扱いにする
+    let ty = &field.ty;
+    let expr = if is_option(ty) {
+        quote! {
+            ::yaml_parser::deserialize::extract_optional_field::<#ty>(value, #key)?.flatten()
+        }
+    } else if options.default {
+        quote! {
+            ::yaml_parser::deserialize::extract_optional_field::<::std::option::Option<#ty>>(value, #key)?
+                .flatten()
+                .unwrap_or_default()
+        }
+    } else {
+        quote! { ::yaml_parser::deserialize::extract_field(value, #key)? }
+    };
+
+    Ok(quote! { #ident: #expr })
+}
+
+fn field_options(field: &Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("yaml")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let value: LitStr = meta.value()?.parse()?;
+                options.rename = Some(value.value());
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                options.default = true;
+                Ok(())
+            } else if meta.path.is_ident("skip") {
+                options.skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown yaml attribute; expected `rename`, `default` or `skip`"))
+            }
+        })?;
+    }
+
+    Ok(options)
+}
+
+// `Option<T>`（`std::option::Option<T>` なども含む）かどうか
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
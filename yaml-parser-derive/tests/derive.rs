@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use yaml_parser::{parse_yaml_to, YamlDeserialize, YamlError, YamlValue};
+
+#[derive(Debug, PartialEq, YamlDeserialize)]
+struct Database {
+    url: String,
+    timeout: Option<f64>,
+    #[yaml(rename = "max-connections")]
+    max_connections: i64,
+}
+
+#[derive(Debug, PartialEq, YamlDeserialize)]
+struct Config {
+    name: String,
+    databases: Vec<Database>,
+    #[yaml(default)]
+    debug: bool,
+    #[yaml(default)]
+    retries: Option<i32>,
+    #[yaml(skip)]
+    cache: HashMap<String, String>,
+    labels: Option<HashMap<String, YamlValue>>,
+}
+
+#[derive(Debug, PartialEq, YamlDeserialize)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+#[derive(Debug, PartialEq, YamlDeserialize)]
+struct Marker;
+
+#[test]
+fn test_derive_nested_struct() {
+    let yaml = "name: app\ndatabases:\n  - url: pg://a\n    max-connections: 10\n  - url: pg://b\n    timeout: 2.5\n    max-connections: 5\nlabels: ~\n";
+    let config: Config = parse_yaml_to(yaml).unwrap();
+
+    assert_eq!(config, Config {
+        name: "app".to_string(),
+        databases: vec![
+            Database { url: "pg://a".to_string(), timeout: None, max_connections: 10 },
+            Database { url: "pg://b".to_string(), timeout: Some(2.5), max_connections: 5 },
+        ],
+        debug: false,
+        retries: None,
+        cache: HashMap::new(),
+        labels: None,
+    });
+}
+
+#[test]
+fn test_derive_missing_required_field() {
+    let result: yaml_parser::Result<Database> = parse_yaml_to("url: pg://a");
+    assert!(matches!(result, Err(YamlError::InvalidValue { message, .. }) if message.contains("max-connections")));
+}
+
+#[test]
+fn test_derive_error_path() {
+    let result: yaml_parser::Result<Config> =
+        parse_yaml_to("name: x\ndatabases:\n  - url: a\n    max-connections: many\n");
+    let error = result.unwrap_err();
+    assert_eq!(error.path(), Some("databases[0].max-connections"));
+    assert_eq!(error.span().map(|span| (span.line, span.column)), Some((4, 22)));
+}
+
+#[test]
+fn test_derive_skip_ignores_present_key() {
+    let config: Config = parse_yaml_to("name: x\ndatabases: []\ncache: 1\ndebug: true").unwrap();
+    assert!(config.cache.is_empty());
+    assert!(config.debug);
+}
+
+#[test]
+fn test_derive_generic_and_unit_structs() {
+    let wrapper: Wrapper<Vec<i64>> = parse_yaml_to("inner: [1, 2]").unwrap();
+    assert_eq!(wrapper.inner, vec![1, 2]);
+
+    assert_eq!(Marker::from_yaml(&YamlValue::Null).unwrap(), Marker);
+}
+
+#[test]
+fn test_derive_null_optional_and_default_fields() {
+    let config: Config = parse_yaml_to("name: x\ndatabases:\n  - url: a\n    timeout: ~\n    max-connections: 1\ndebug: null\nretries: ~\n").unwrap();
+    assert_eq!(config.databases[0].timeout, None);
+    assert!(!config.debug);
+    assert_eq!(config.retries, None);
+
+    // マッピングでなければエラー
+    assert!(Database::from_yaml(&YamlValue::Null).is_err());
+}